@@ -1,8 +1,13 @@
-use reqwest::blocking::multipart::Form;
-use reqwest::blocking::Client;
-use serde::Deserialize;
+use chrono::{DateTime, Local};
+use reqwest::blocking::multipart::{Form, Part};
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use reqwest::header::HeaderMap;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
 use std::path::Path;
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Deserialize, Debug)]
@@ -14,47 +19,249 @@ pub struct FileUploadEntry {
 #[derive(Deserialize, Debug)]
 pub struct FileUploadResponse {
     id: u64,
+    size: u64,
 }
 
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("location header missing in redirect response")]
     NoRedirectLocation,
+    #[error("uploaded {name} has size {remote} on canvas, but {local} locally")]
+    IntegrityMismatch {
+        name: String,
+        local: u64,
+        remote: u64,
+    },
+    #[error("the checkout was accepted but canvas did not record a submission")]
+    NotSubmitted,
+    #[error("the file name {0:?} is empty once its control characters are removed")]
+    InvalidName(String),
     #[error(transparent)]
     ToString(#[from] reqwest::header::ToStrError),
     #[error(transparent)]
     FileSystem(#[from] std::io::Error),
     #[error(transparent)]
     Reqwest(#[from] reqwest::Error),
+    #[error("unexpected response from canvas: {0}")]
+    Json(#[from] serde_json::Error),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// The path of the Canvas REST API below the domain.
+pub const API_BASE: &str = "api/v1";
+
+/// The url of the API endpoint `path` at `domain`, e.g. `courses` for
+/// `https://<domain>/api/v1/courses`.
+fn api_url(domain: &str, path: &str) -> String {
+    format!("https://{}/{}/{}", domain, API_BASE, path)
+}
+
+/// Send `request` with `client`, failing on an error status. The method and url of every
+/// request are logged at debug level before it is sent, and the status of its response after,
+/// along with what the request cost of the rate limit and how much remains.
+fn send(client: &Client, request: RequestBuilder) -> Result<Response> {
+    let request = request.build()?;
+    log::debug!("{} {}", request.method(), request.url());
+    let response = client.execute(request)?;
+    match rate_limit(response.headers()) {
+        (Some(cost), Some(remaining)) => log::debug!(
+            "{} {} (cost {:.1}, {:.1} remaining of the rate limit)",
+            response.status(),
+            response.url(),
+            cost,
+            remaining
+        ),
+        _ => log::debug!("{} {}", response.status(), response.url()),
+    }
+    Ok(response.error_for_status()?)
+}
+
+/// The cost of a request and the budget remaining for further requests, as reported by Canvas
+/// in the `X-Request-Cost` and `X-Rate-Limit-Remaining` headers. Canvas throttles requests once
+/// the budget runs out.
+fn rate_limit(headers: &HeaderMap) -> (Option<f64>, Option<f64>) {
+    let parse = |name| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok())
+    };
+    (parse("x-request-cost"), parse("x-rate-limit-remaining"))
+}
+
+/// How a list is fetched from Canvas: the number of items per page, which Canvas caps at 100,
+/// and optionally a cap on the number of items fetched in total.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Paging {
+    per_page: u32,
+    max_items: Option<usize>,
+}
+
+impl Paging {
+    /// The largest page size Canvas serves.
+    pub const MAX_PER_PAGE: u32 = 100;
+
+    /// A `per_page` outside 1 to 100 is clamped, as Canvas would otherwise cap it silently.
+    pub fn new(per_page: u32, max_items: Option<usize>) -> Self {
+        Self {
+            per_page: per_page.clamp(1, Self::MAX_PER_PAGE),
+            max_items,
+        }
+    }
+
+    pub fn per_page(&self) -> u32 {
+        self.per_page
+    }
+
+    pub fn max_items(&self) -> Option<usize> {
+        self.max_items
+    }
+}
+
+impl Default for Paging {
+    fn default() -> Self {
+        Self::new(Self::MAX_PER_PAGE, None)
+    }
+}
+
+/// The url of the next page, from the `Link` header Canvas gives with a paginated list.
+fn next_page(headers: &HeaderMap) -> Option<String> {
+    let links = headers.get(reqwest::header::LINK)?.to_str().ok()?;
+    links.split(',').find_map(|link| {
+        let (url, params) = link.split_once(';')?;
+        if params
+            .split(';')
+            .any(|param| param.trim() == "rel=\"next\"")
+        {
+            Some(
+                url.trim()
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .to_owned(),
+            )
+        } else {
+            None
+        }
+    })
+}
+
+/// Fetch the list at `url` page by page, following the `next` links, until the list ends or
+/// `paging.max_items` items have been fetched.
+fn get_list<T: DeserializeOwned>(auth: &str, url: &str, paging: Paging) -> Result<Vec<T>> {
+    let client = Client::new();
+    let mut items = Vec::new();
+    // the links to the following pages carry the page size
+    let mut request = client.get(url).query(&[("per_page", paging.per_page)]);
+    loop {
+        let response = send(&client, request.bearer_auth(auth))?;
+        let next = next_page(response.headers());
+        items.extend(json::<Vec<T>>(response)?);
+        if let Some(max_items) = paging.max_items {
+            if items.len() >= max_items {
+                items.truncate(max_items);
+                return Ok(items);
+            }
+        }
+        match next {
+            Some(next) => request = client.get(next),
+            None => return Ok(items),
+        }
+    }
+}
+
+/// Deserialize the body of `response`, which is logged at trace level.
+fn json<T: DeserializeOwned>(response: Response) -> Result<T> {
+    let body = response.text()?;
+    log::trace!("{}", body);
+    Ok(serde_json::from_str(&body)?)
+}
+
+/// The submission type of an assignment submitted as uploaded files.
+pub const ONLINE_UPLOAD: &str = "online_upload";
+
+/// The submission type of an assignment submitted as a text entry.
+pub const ONLINE_TEXT_ENTRY: &str = "online_text_entry";
+
+/// The submission type of an assignment submitted as a url.
+pub const ONLINE_URL: &str = "online_url";
+
+/// The submission type of an assignment submitted as an audio or video recording.
+pub const MEDIA_RECORDING: &str = "media_recording";
+
+/// `name` without control characters and surrounding whitespace, which Canvas may reject with a
+/// confusing error. Fails if nothing is left.
+fn sanitize_payload_name(name: &str) -> Result<String> {
+    let sanitized: String = name.chars().filter(|c| !c.is_control()).collect();
+    let sanitized = sanitized.trim();
+    if sanitized.is_empty() {
+        return Err(Error::InvalidName(name.to_owned()));
+    }
+    Ok(sanitized.to_owned())
+}
+
+/// The client for uploading files as the user of the `auth` token at `domain`, shared between
+/// the uploads of a submission so that they reuse their connections. Its timeout is separate
+/// from the default one of 30 seconds, which suits the other requests but may cut off the
+/// upload of a large file.
+#[derive(Clone, Debug)]
+pub struct UploadClient {
+    client: Client,
+    auth: String,
+    domain: String,
+}
+
+impl UploadClient {
+    /// The timeout for uploading a file when none is configured.
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(300);
+
+    /// A client which gives up on a request, the upload of a file included, after `timeout`.
+    pub fn new(auth: &str, domain: &str, timeout: Duration) -> Result<Self> {
+        let client = Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .timeout(timeout)
+            .build()?;
+        Ok(Self {
+            client,
+            auth: auth.to_owned(),
+            domain: domain.to_owned(),
+        })
+    }
+}
+
+/// Upload the file at `payload_path` under the name `payload_name` with `client`, to be
+/// included in a submission. Unless `content_type` is given, the content type is guessed from the file.
+/// Returns the `file_id` of the uploaded file.
 pub fn submit_assignment_upload<P: AsRef<Path>>(
-    auth: &str,
-    domain: &str,
+    client: &UploadClient,
     course_id: u64,
     assignment_id: u64,
     payload_path: P,
     payload_name: &str,
+    content_type: Option<&str>,
 ) -> Result<u64> {
-    let client = Client::builder()
-        .redirect(reqwest::redirect::Policy::none())
-        .build()?;
-
+    let UploadClient {
+        client,
+        auth,
+        domain,
+    } = client;
+    let payload_name = &sanitize_payload_name(payload_name)?;
     let payload_data = payload_path.as_ref().metadata()?;
 
-    let entry = client
-        .post(&format!(
-            "https://{}/api/v1/courses/{}/assignments/{}/submissions/self/files",
-            domain, course_id, assignment_id
-        ))
-        .query(&[("name", payload_name)])
-        .query(&[("size", payload_data.len())])
-        .bearer_auth(auth)
-        .send()?
-        .error_for_status()?
-        .json::<FileUploadEntry>()?;
+    let entry: FileUploadEntry = json(send(
+        client,
+        client
+            .post(api_url(
+                domain,
+                &format!(
+                    "courses/{}/assignments/{}/submissions/self/files",
+                    course_id, assignment_id
+                ),
+            ))
+            .query(&[("name", payload_name)])
+            .query(&[("size", payload_data.len())])
+            .bearer_auth(auth),
+    )?)?;
 
     let (url, params) = (entry.upload_url, entry.upload_params);
 
@@ -62,13 +269,13 @@ pub fn submit_assignment_upload<P: AsRef<Path>>(
     for (key, val) in params.into_iter() {
         form = form.text(key, val);
     }
-    form = form.file("file", payload_path)?;
+    let mut part = Part::file(payload_path)?;
+    if let Some(content_type) = content_type {
+        part = part.mime_str(content_type)?;
+    }
+    form = form.part("file", part);
 
-    let mut upload = client
-        .post(&url)
-        .multipart(form)
-        .send()?
-        .error_for_status()?;
+    let mut upload = send(client, client.post(&url).multipart(form))?;
 
     if upload.status().is_redirection() {
         let redirect_url = upload
@@ -76,25 +283,209 @@ pub fn submit_assignment_upload<P: AsRef<Path>>(
             .get(reqwest::header::LOCATION)
             .ok_or_else(|| Error::NoRedirectLocation)?
             .to_str()?;
-        upload = client
-            .get(redirect_url)
-            .bearer_auth(auth)
-            .send()?
-            .error_for_status()?;
+        upload = send(client, client.get(redirect_url).bearer_auth(auth))?;
     };
 
-    Ok(upload.json::<FileUploadResponse>()?.id)
+    // guard against truncated uploads
+    let uploaded: FileUploadResponse = json(upload)?;
+    if uploaded.size != payload_data.len() {
+        return Err(Error::IntegrityMismatch {
+            name: payload_name.to_owned(),
+            local: payload_data.len(),
+            remote: uploaded.size,
+        });
+    }
+
+    Ok(uploaded.id)
+}
+
+/// A submission as recorded by Canvas.
+#[derive(Clone, Deserialize, Debug)]
+pub struct Submission {
+    id: u64,
+    submitted_at: Option<DateTime<Local>>,
+    score: Option<f64>,
+    grade: Option<String>,
+    workflow_state: String,
+    attempt: Option<u64>,
+    submission_type: Option<String>,
+    url: Option<String>,
+    #[serde(default)]
+    late: bool,
+    seconds_late: Option<u64>,
+    #[serde(default)]
+    attachments: Vec<Attachment>,
+    #[serde(default)]
+    submission_history: Vec<Submission>,
+    #[serde(default)]
+    submission_comments: Vec<SubmissionComment>,
+}
+
+/// A comment on a submission, by the teacher or the student.
+#[derive(Clone, Deserialize, Debug)]
+pub struct SubmissionComment {
+    author_name: String,
+    comment: String,
+    created_at: DateTime<Local>,
+    attempt: Option<u64>,
+}
+
+impl SubmissionComment {
+    pub fn author_name(&self) -> &str {
+        &self.author_name
+    }
+
+    pub fn comment(&self) -> &str {
+        &self.comment
+    }
+
+    pub fn created_at(&self) -> &DateTime<Local> {
+        &self.created_at
+    }
+
+    /// The attempt which the comment was made on, if Canvas records it.
+    pub fn attempt(&self) -> Option<u64> {
+        self.attempt
+    }
+}
+
+/// What a submission consists of, by its submission type. Displayed as e.g. "text entry" or
+/// "upload: a.pdf, b.pdf".
+#[derive(Clone, Copy, Debug)]
+pub enum SubmissionContent<'a> {
+    Upload(&'a [Attachment]),
+    TextEntry,
+    Url(&'a str),
+    MediaRecording,
+    /// A submission type without a description of its own, such as "basic_lti_launch".
+    Other(&'a str),
+}
+
+impl fmt::Display for SubmissionContent<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubmissionContent::Upload(attachments) => {
+                let names: Vec<_> = attachments.iter().map(Attachment::display_name).collect();
+                write!(f, "upload: {}", names.join(", "))
+            }
+            SubmissionContent::TextEntry => write!(f, "text entry"),
+            SubmissionContent::Url(url) => write!(f, "url: {}", url),
+            SubmissionContent::MediaRecording => write!(f, "media recording"),
+            SubmissionContent::Other(submission_type) => write!(f, "{}", submission_type),
+        }
+    }
+}
+
+/// A file attached to a submission.
+#[derive(Clone, Deserialize, Debug)]
+pub struct Attachment {
+    id: u64,
+    display_name: String,
+    size: u64,
+}
+
+impl Attachment {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The name of the file as uploaded.
+    pub fn display_name(&self) -> &str {
+        &self.display_name
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+impl Submission {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn submitted_at(&self) -> Option<&DateTime<Local>> {
+        self.submitted_at.as_ref()
+    }
+
+    /// The score, if the submission has been graded.
+    pub fn score(&self) -> Option<f64> {
+        self.score
+    }
+
+    /// The grade as displayed by Canvas (e.g. a letter grade or "complete"), if the submission
+    /// has been graded.
+    pub fn grade(&self) -> Option<&str> {
+        self.grade.as_deref()
+    }
+
+    /// One of "submitted", "unsubmitted", "graded" or "pending_review".
+    pub fn workflow_state(&self) -> &str {
+        &self.workflow_state
+    }
+
+    /// Whether anything has been submitted. For an assignment without submissions, Canvas still
+    /// returns a submission, "unsubmitted" and without a submission time. A submitted submission
+    /// always has a submission time.
+    pub fn is_submitted(&self) -> bool {
+        self.workflow_state != "unsubmitted" && self.submitted_at.is_some()
+    }
+
+    /// The number of the attempt, counting from 1, or `None` if nothing has been submitted.
+    pub fn attempt(&self) -> Option<u64> {
+        self.attempt
+    }
+
+    /// Whether Canvas marks the submission as late, i.e. submitted after the due date.
+    pub fn is_late(&self) -> bool {
+        self.late
+    }
+
+    /// How many seconds after the due date the submission was made, if it is late.
+    pub fn seconds_late(&self) -> Option<u64> {
+        self.seconds_late.filter(|_| self.late)
+    }
+
+    /// The uploaded files, for a submission of type `ONLINE_UPLOAD`.
+    pub fn attachments(&self) -> &[Attachment] {
+        &self.attachments
+    }
+
+    /// What was submitted, or `None` if nothing has been submitted.
+    pub fn content(&self) -> Option<SubmissionContent<'_>> {
+        let content = match self.submission_type.as_deref()? {
+            ONLINE_UPLOAD => SubmissionContent::Upload(&self.attachments),
+            ONLINE_TEXT_ENTRY => SubmissionContent::TextEntry,
+            ONLINE_URL => SubmissionContent::Url(self.url.as_deref().unwrap_or_default()),
+            MEDIA_RECORDING => SubmissionContent::MediaRecording,
+            other => SubmissionContent::Other(other),
+        };
+        Some(content)
+    }
+
+    /// Every attempt of the submission, the current one included. Only present for a submission
+    /// fetched with `get_own_submission`.
+    pub fn history(&self) -> &[Submission] {
+        &self.submission_history
+    }
+
+    /// The comments on the submission, oldest first. Only present for a submission fetched with
+    /// `get_own_submission`, including `GetSubmissionInclude::SubmissionComments`.
+    pub fn comments(&self) -> &[SubmissionComment] {
+        &self.submission_comments
+    }
 }
 
 /// After uploading the files, we need to confirm that they shall be included in a submission.
 /// The parameter `file_ids` contains the `file_id` of each uploaded file to be included.
+/// Returns the submission which Canvas created.
 pub fn submit_assignment_checkout(
     auth: &str,
     domain: &str,
     course_id: u64,
     assignment_id: u64,
     file_ids: Vec<u64>,
-) -> Result<()> {
+) -> Result<Submission> {
     let client = reqwest::blocking::Client::new();
 
     let file_ids_query = file_ids
@@ -102,20 +493,133 @@ pub fn submit_assignment_checkout(
         .map(|id| ("submission[file_ids][]", id))
         .collect::<Vec<(&str, u64)>>();
 
-    let _submit = client
-        .post(&format!(
-            "https://{}/api/v1/courses/{}/assignments/{}/submissions",
-            domain, course_id, assignment_id
-        ))
-        .query(&[("submission[submission_type]", "online_upload")])
-        .query(&file_ids_query)
-        .bearer_auth(auth)
-        .send()?
-        .error_for_status()?;
-    // logic about how to handle requests where the response indicates that something bad happened
-    // should maybe go here
+    let submission: Submission = json(send(
+        &client,
+        client
+            .post(api_url(
+                domain,
+                &format!(
+                    "courses/{}/assignments/{}/submissions",
+                    course_id, assignment_id
+                ),
+            ))
+            .query(&[("submission[submission_type]", ONLINE_UPLOAD)])
+            .query(&file_ids_query)
+            .bearer_auth(auth),
+    )?)?;
+
+    // Canvas may answer with a success status without having recorded the submission
+    if !submission.is_submitted() {
+        return Err(Error::NotSubmitted);
+    }
+
+    Ok(submission)
+}
 
-    Ok(())
+/// Submit `body` as a text entry. Canvas treats the body as HTML. Returns the submission which
+/// Canvas created.
+pub fn submit_text_entry(
+    auth: &str,
+    domain: &str,
+    course_id: u64,
+    assignment_id: u64,
+    body: &str,
+) -> Result<Submission> {
+    let client = Client::new();
+    let submission: Submission = json(send(
+        &client,
+        client
+            .post(api_url(
+                domain,
+                &format!(
+                    "courses/{}/assignments/{}/submissions",
+                    course_id, assignment_id
+                ),
+            ))
+            .form(&[
+                ("submission[submission_type]", ONLINE_TEXT_ENTRY),
+                ("submission[body]", body),
+            ])
+            .bearer_auth(auth),
+    )?)?;
+
+    if !submission.is_submitted() {
+        return Err(Error::NotSubmitted);
+    }
+
+    Ok(submission)
+}
+
+/// What Canvas may include along with a submission, on request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GetSubmissionInclude {
+    /// Every attempt of the submission, see `Submission::history`.
+    SubmissionHistory,
+    /// The comments on the submission, see `Submission::comments`.
+    SubmissionComments,
+    RubricAssessment,
+}
+
+/// Returns the submission of the user associated with the `auth` token. Canvas returns a
+/// submission even if nothing has been submitted, with the `workflow_state` "unsubmitted".
+/// Whatever is listed in `include` is fetched along with it.
+pub fn get_own_submission(
+    auth: &str,
+    domain: &str,
+    course_id: u64,
+    assignment_id: u64,
+    include: &[GetSubmissionInclude],
+) -> Result<Submission> {
+    let include: Vec<_> = include
+        .iter()
+        .map(|include| ("include[]", include))
+        .collect();
+    let client = Client::new();
+    json(send(
+        &client,
+        client
+            .get(api_url(
+                domain,
+                &format!(
+                    "courses/{}/assignments/{}/submissions/self",
+                    course_id, assignment_id
+                ),
+            ))
+            .query(&include)
+            .bearer_auth(auth),
+    )?)
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct User {
+    id: u64,
+    name: String,
+    login_id: Option<String>,
+}
+
+impl User {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The login, which Canvas only reveals to users with the permission to see it.
+    pub fn login_id(&self) -> Option<&str> {
+        self.login_id.as_deref()
+    }
+}
+
+/// Returns the user associated with the `auth` token.
+pub fn get_self(auth: &str, domain: &str) -> Result<User> {
+    let client = Client::new();
+    json(send(
+        &client,
+        client.get(api_url(domain, "users/self")).bearer_auth(auth),
+    )?)
 }
 
 #[derive(Clone, Deserialize, Debug)]
@@ -135,19 +639,28 @@ impl Course {
 }
 
 /// Returns the `id` and `name` of each course associated with the `auth` token.
-pub fn get_courses(token: &str, domain: &str) -> Result<Vec<Course>> {
-    Ok(Client::new()
-        .get(&format!("https://{}/api/v1/courses", domain))
-        .bearer_auth(token)
-        .send()?
-        .error_for_status()?
-        .json()?)
+pub fn get_courses(token: &str, domain: &str, paging: Paging) -> Result<Vec<Course>> {
+    get_list(token, &api_url(domain, "courses"), paging)
 }
 
 #[derive(Clone, Deserialize, Debug)]
 pub struct Assignment {
     id: u64,
     name: String,
+    due_at: Option<DateTime<Local>>,
+    lock_at: Option<DateTime<Local>>,
+    unlock_at: Option<DateTime<Local>>,
+    points_possible: Option<f64>,
+    #[serde(default)]
+    submission_types: Vec<String>,
+    group_category_id: Option<u64>,
+    #[serde(default)]
+    grade_group_students_individually: bool,
+    html_url: Option<String>,
+    description: Option<String>,
+    assignment_group_id: Option<u64>,
+    #[serde(default)]
+    allowed_extensions: Vec<String>,
 }
 
 impl Assignment {
@@ -158,6 +671,145 @@ impl Assignment {
     pub fn id(&self) -> u64 {
         self.id
     }
+
+    /// The due date, which Canvas leaves out for undated assignments.
+    pub fn due_at(&self) -> Option<&DateTime<Local>> {
+        self.due_at.as_ref()
+    }
+
+    pub fn lock_at(&self) -> Option<&DateTime<Local>> {
+        self.lock_at.as_ref()
+    }
+
+    pub fn unlock_at(&self) -> Option<&DateTime<Local>> {
+        self.unlock_at.as_ref()
+    }
+
+    pub fn points_possible(&self) -> Option<f64> {
+        self.points_possible
+    }
+
+    /// Order by due date, the earliest first and undated assignments last.
+    pub fn cmp_due(&self, other: &Self) -> std::cmp::Ordering {
+        match (self.due_at, other.due_at) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
+        }
+    }
+
+    /// The id of the assignment group which the assignment belongs to.
+    pub fn assignment_group_id(&self) -> Option<u64> {
+        self.assignment_group_id
+    }
+
+    /// The address of the assignment's page on Canvas, for opening it in a browser.
+    pub fn html_url(&self) -> Option<&str> {
+        self.html_url.as_deref()
+    }
+
+    /// The description written by the teacher, as the HTML of the assignment's page.
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn submission_types(&self) -> &[String] {
+        &self.submission_types
+    }
+
+    /// The group set of a group assignment.
+    pub fn group_category_id(&self) -> Option<u64> {
+        self.group_category_id
+    }
+
+    /// Whether a submission counts for the whole group of the submitting user.
+    pub fn is_group_assignment(&self) -> bool {
+        self.group_category_id.is_some()
+    }
+
+    /// Whether the members of a group are graded individually, despite submitting together.
+    pub fn grade_group_students_individually(&self) -> bool {
+        self.grade_group_students_individually
+    }
+
+    /// Whether the assignment accepts submissions of type `submission_type`, such as
+    /// `ONLINE_UPLOAD`. If Canvas did not report any types, we assume that it does.
+    pub fn accepts(&self, submission_type: &str) -> bool {
+        self.submission_types.is_empty()
+            || self.submission_types.iter().any(|t| t == submission_type)
+    }
+
+    /// The file extensions, such as "pdf", which uploads are restricted to. Empty if any file
+    /// may be uploaded.
+    pub fn allowed_extensions(&self) -> &[String] {
+        &self.allowed_extensions
+    }
+
+    /// Whether a file named `file_name` may be uploaded to the assignment, by its extension.
+    /// Canvas compares the extensions ignoring case.
+    pub fn accepts_file_name(&self, file_name: &str) -> bool {
+        if self.allowed_extensions.is_empty() {
+            return true;
+        }
+        match file_name.rsplit_once('.') {
+            Some((_, extension)) => self
+                .allowed_extensions
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(extension)),
+            None => false,
+        }
+    }
+
+    /// Whether the assignment has been locked at the time `now`, after which Canvas refuses
+    /// any new submissions.
+    pub fn is_locked(&self, now: &DateTime<Local>) -> bool {
+        self.lock_at.is_some_and(|lock_at| *now >= lock_at)
+    }
+
+    /// Whether the assignment has been unlocked at the time `now`. An assignment without an
+    /// `unlock_at` date is always open.
+    pub fn is_open(&self, now: &DateTime<Local>) -> bool {
+        self.unlock_at.is_none_or(|unlock_at| *now >= unlock_at)
+    }
+}
+
+/// A group of assignments within a course, such as "Labs" or "Exams".
+#[derive(Clone, Deserialize, Debug)]
+pub struct AssignmentGroup {
+    id: u64,
+    name: String,
+    #[serde(default)]
+    position: u64,
+}
+
+impl AssignmentGroup {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The place of the group in the course's list of groups, counting from 1.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+}
+
+/// Returns the assignment groups of the course `course_id`.
+pub fn get_assignment_groups(
+    token: &str,
+    domain: &str,
+    course_id: u64,
+    paging: Paging,
+) -> Result<Vec<AssignmentGroup>> {
+    get_list(
+        token,
+        &api_url(domain, &format!("courses/{}/assignment_groups", course_id)),
+        paging,
+    )
 }
 
 #[derive(Deserialize, Debug)]
@@ -172,22 +824,363 @@ pub enum Bucket {
     Future,
 }
 
-pub fn get_assignments(token: &str, domain: &str, course_id: u64) -> Result<Vec<Assignment>> {
-    Ok(Client::new()
-        .get(&format!(
-            "https://{}/api/v1/courses/{}/assignments",
-            domain, course_id
-        ))
-        .bearer_auth(token)
-        .send()?
-        .error_for_status()?
-        .json()?)
+pub fn get_assignments(
+    token: &str,
+    domain: &str,
+    course_id: u64,
+    paging: Paging,
+) -> Result<Vec<Assignment>> {
+    get_list(
+        token,
+        &api_url(domain, &format!("courses/{}/assignments", course_id)),
+        paging,
+    )
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     #[test]
     fn it_works() {
         assert_eq!(2 + 2, 4);
     }
+
+    #[test]
+    fn api_urls() {
+        assert_eq!(
+            api_url("example.instructure.com", "users/self"),
+            "https://example.instructure.com/api/v1/users/self"
+        );
+    }
+
+    #[test]
+    fn submission_include_query() {
+        let include = [
+            GetSubmissionInclude::SubmissionHistory,
+            GetSubmissionInclude::RubricAssessment,
+        ];
+        let include: Vec<_> = include
+            .iter()
+            .map(|include| ("include[]", include))
+            .collect();
+        let request = Client::new()
+            .get("https://d/")
+            .query(&include)
+            .build()
+            .unwrap();
+        assert_eq!(
+            request.url().query(),
+            Some("include%5B%5D=submission_history&include%5B%5D=rubric_assessment")
+        );
+    }
+
+    #[test]
+    fn payload_names() {
+        assert_eq!(sanitize_payload_name("report.pdf").unwrap(), "report.pdf");
+        assert_eq!(
+            sanitize_payload_name(" rap\u{7}port\t.pdf\n").unwrap(),
+            "rapport.pdf"
+        );
+        assert!(matches!(
+            sanitize_payload_name("\u{1b}\r\n"),
+            Err(Error::InvalidName(_))
+        ));
+        assert!(matches!(
+            sanitize_payload_name(""),
+            Err(Error::InvalidName(_))
+        ));
+    }
+
+    #[test]
+    fn rate_limit_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(rate_limit(&headers), (None, None));
+        headers.insert("X-Request-Cost", "1.5".parse().unwrap());
+        headers.insert("X-Rate-Limit-Remaining", "698.5".parse().unwrap());
+        assert_eq!(rate_limit(&headers), (Some(1.5), Some(698.5)));
+    }
+
+    #[test]
+    fn pagination_links() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            reqwest::header::LINK,
+            "<https://d/api/v1/courses?page=1&per_page=100>; rel=\"current\",\
+             <https://d/api/v1/courses?page=2&per_page=100>; rel=\"next\",\
+             <https://d/api/v1/courses?page=1&per_page=100>; rel=\"first\""
+                .parse()
+                .unwrap(),
+        );
+        assert_eq!(
+            next_page(&headers).as_deref(),
+            Some("https://d/api/v1/courses?page=2&per_page=100")
+        );
+        headers.insert(
+            reqwest::header::LINK,
+            "<https://d/api/v1/courses?page=2&per_page=100>; rel=\"current\""
+                .parse()
+                .unwrap(),
+        );
+        assert_eq!(next_page(&headers), None);
+
+        assert_eq!(Paging::new(500, None).per_page(), 100);
+        assert_eq!(Paging::new(0, Some(5)).per_page(), 1);
+    }
+
+    #[test]
+    fn assignment_lock_dates() {
+        let assignment: Assignment = serde_json::from_str(
+            r#"{
+                "id": 1,
+                "name": "Assignment 1",
+                "lock_at": "2021-03-01T23:59:00Z",
+                "unlock_at": "2021-02-01T08:00:00Z",
+                "html_url": "https://example.instructure.com/courses/2/assignments/1",
+                "description": "<p>Hand in a report.</p>",
+                "assignment_group_id": 3
+            }"#,
+        )
+        .expect("ought to be a valid assignment");
+
+        let before = "2021-01-15T12:00:00Z".parse().unwrap();
+        let during = "2021-02-15T12:00:00Z".parse().unwrap();
+        let after = "2021-03-15T12:00:00Z".parse().unwrap();
+
+        assert!(!assignment.is_open(&before) && !assignment.is_locked(&before));
+        assert!(assignment.is_open(&during) && !assignment.is_locked(&during));
+        assert!(assignment.is_locked(&after));
+        assert_eq!(assignment.due_at(), None);
+        assert_eq!(
+            assignment.html_url(),
+            Some("https://example.instructure.com/courses/2/assignments/1")
+        );
+        assert_eq!(assignment.description(), Some("<p>Hand in a report.</p>"));
+        assert_eq!(assignment.assignment_group_id(), Some(3));
+    }
+
+    #[test]
+    fn assignments_by_due_date() {
+        let assignment = |id, due_at: &str| -> Assignment {
+            serde_json::from_str(&format!(
+                r#"{{ "id": {}, "name": "Assignment", "due_at": {} }}"#,
+                id, due_at
+            ))
+            .expect("ought to be a valid assignment")
+        };
+        let mut assignments = [
+            assignment(1, "null"),
+            assignment(2, r#""2021-03-01T23:59:00Z""#),
+            assignment(3, r#""2021-02-01T23:59:00Z""#),
+        ];
+        assignments.sort_by(Assignment::cmp_due);
+        let ids: Vec<_> = assignments.iter().map(Assignment::id).collect();
+        assert_eq!(ids, [3, 2, 1]);
+    }
+
+    #[test]
+    fn assignment_submission_types() {
+        let assignment: Assignment = serde_json::from_str(
+            r#"{
+                "id": 1,
+                "name": "Essay",
+                "points_possible": 20.0,
+                "submission_types": ["online_text_entry", "online_url"]
+            }"#,
+        )
+        .expect("ought to be a valid assignment");
+        assert!(!assignment.accepts(ONLINE_UPLOAD));
+        assert!(assignment.accepts("online_url"));
+        assert_eq!(assignment.points_possible(), Some(20.0));
+        assert!(assignment.allowed_extensions().is_empty());
+        assert!(assignment.accepts_file_name("essay"));
+    }
+
+    #[test]
+    fn assignment_allowed_extensions() {
+        let assignment: Assignment = serde_json::from_str(
+            r#"{
+                "id": 1,
+                "name": "Report",
+                "submission_types": ["online_upload"],
+                "allowed_extensions": ["pdf", "zip"]
+            }"#,
+        )
+        .expect("ought to be a valid assignment");
+        assert_eq!(assignment.allowed_extensions(), ["pdf", "zip"]);
+        assert!(assignment.accepts_file_name("report.pdf"));
+        assert!(assignment.accepts_file_name("code.tar.ZIP"));
+        assert!(!assignment.accepts_file_name("report.docx"));
+        assert!(!assignment.accepts_file_name("pdf"));
+    }
+
+    #[test]
+    fn submission_grades() {
+        let ungraded: Submission = serde_json::from_str(
+            r#"{
+                "id": 7,
+                "submitted_at": "2021-02-15T12:00:00Z",
+                "score": null,
+                "grade": null,
+                "workflow_state": "submitted"
+            }"#,
+        )
+        .expect("ought to be a valid submission");
+        assert_eq!(ungraded.score(), None);
+        assert_eq!(ungraded.grade(), None);
+        assert!(!ungraded.is_late());
+        assert_eq!(ungraded.seconds_late(), None);
+
+        let graded: Submission = serde_json::from_str(
+            r#"{
+                "id": 7,
+                "submitted_at": "2021-02-15T12:00:00Z",
+                "score": 9.0,
+                "grade": "9",
+                "workflow_state": "graded",
+                "late": true,
+                "seconds_late": 8100,
+                "attachments": [ { "id": 3, "display_name": "report.pdf", "size": 1024 } ]
+            }"#,
+        )
+        .expect("ought to be a valid submission");
+        assert!(graded.is_late());
+        assert_eq!(graded.seconds_late(), Some(8100));
+        assert_eq!(graded.score(), Some(9.0));
+        assert_eq!(graded.grade(), Some("9"));
+        assert_eq!(graded.workflow_state(), "graded");
+        assert_eq!(ungraded.attachments().len(), 0);
+        assert_eq!(graded.attachments()[0].display_name(), "report.pdf");
+        assert!(graded.history().is_empty());
+    }
+
+    #[test]
+    fn unsubmitted_submission() {
+        let unsubmitted: Submission = serde_json::from_str(
+            r#"{
+                "id": 7,
+                "user_id": 3,
+                "assignment_id": 5,
+                "submitted_at": null,
+                "graded_at": null,
+                "score": null,
+                "grade": null,
+                "attempt": null,
+                "submission_type": null,
+                "workflow_state": "unsubmitted",
+                "submission_history": [
+                    {
+                        "id": 7,
+                        "submitted_at": null,
+                        "score": null,
+                        "grade": null,
+                        "attempt": null,
+                        "workflow_state": "unsubmitted"
+                    }
+                ]
+            }"#,
+        )
+        .expect("ought to be a valid submission");
+        assert!(!unsubmitted.is_submitted());
+        assert_eq!(unsubmitted.attempt(), None);
+        assert!(unsubmitted.attachments().is_empty());
+        assert!(!unsubmitted.history()[0].is_submitted());
+    }
+
+    #[test]
+    fn submission_history() {
+        let submission: Submission = serde_json::from_str(
+            r#"{
+                "id": 7,
+                "submitted_at": "2021-02-16T12:00:00Z",
+                "score": null,
+                "grade": null,
+                "workflow_state": "submitted",
+                "attempt": 2,
+                "submission_history": [
+                    {
+                        "id": 7,
+                        "submitted_at": "2021-02-15T12:00:00Z",
+                        "score": null,
+                        "grade": null,
+                        "workflow_state": "submitted",
+                        "attempt": 1,
+                        "submission_type": "online_upload",
+                        "attachments": [ { "id": 3, "display_name": "old.pdf", "size": 512 } ]
+                    },
+                    {
+                        "id": 7,
+                        "submitted_at": "2021-02-16T12:00:00Z",
+                        "score": null,
+                        "grade": null,
+                        "workflow_state": "submitted",
+                        "attempt": 2,
+                        "attachments": [ { "id": 4, "display_name": "new.pdf", "size": 1024 } ]
+                    }
+                ],
+                "submission_comments": [
+                    {
+                        "id": 9,
+                        "author_id": 2,
+                        "author_name": "Teacher",
+                        "comment": "See the feedback in the margins.",
+                        "created_at": "2021-02-17T09:00:00Z",
+                        "attempt": 2
+                    }
+                ]
+            }"#,
+        )
+        .expect("ought to be a valid submission");
+        assert_eq!(submission.attempt(), Some(2));
+        assert_eq!(submission.history().len(), 2);
+        assert_eq!(submission.history()[0].attempt(), Some(1));
+        assert_eq!(
+            submission.history()[0].attachments()[0].display_name(),
+            "old.pdf"
+        );
+        assert_eq!(
+            submission.history()[0].content().unwrap().to_string(),
+            "upload: old.pdf"
+        );
+        assert_eq!(submission.history()[0].comments().len(), 0);
+        assert_eq!(submission.comments()[0].author_name(), "Teacher");
+        assert_eq!(submission.comments()[0].attempt(), Some(2));
+    }
+
+    #[test]
+    fn submission_contents() {
+        let content = |submission_type: &str| {
+            let json = format!(
+                r#"{{
+                    "id": 7,
+                    "submitted_at": "2021-02-16T12:00:00Z",
+                    "workflow_state": "submitted",
+                    "submission_type": {},
+                    "url": "https://example.com/report"
+                }}"#,
+                submission_type
+            );
+            serde_json::from_str::<Submission>(&json)
+                .expect("ought to be a valid submission")
+                .content()
+                .map(|content| content.to_string())
+        };
+        assert_eq!(content("null"), None);
+        assert_eq!(
+            content(r#""online_text_entry""#).as_deref(),
+            Some("text entry")
+        );
+        assert_eq!(
+            content(r#""online_url""#).as_deref(),
+            Some("url: https://example.com/report")
+        );
+        assert_eq!(
+            content(r#""media_recording""#).as_deref(),
+            Some("media recording")
+        );
+        assert_eq!(
+            content(r#""basic_lti_launch""#).as_deref(),
+            Some("basic_lti_launch")
+        );
+    }
 }
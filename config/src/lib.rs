@@ -1,51 +1,263 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize, Serializer};
 use std::borrow::Borrow;
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::{env, fs};
 use thiserror::Error;
+use url::Url;
 
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Clone, Deserialize, Serialize, Debug, PartialEq)]
 pub struct Config {
-    token: String,
-    domain: String,
+    #[serde(serialize_with = "redact")]
+    token: Option<String>,
+    token_file: Option<String>,
+    domain: Option<String>,
+    default_assignment: Option<String>,
+    max_upload_bytes: Option<u64>,
+    upload_timeout_secs: Option<u64>,
+    per_page: Option<u32>,
+    max_items: Option<usize>,
+    timezone: Option<String>,
+    #[serde(default)]
+    defaults: Defaults,
     course: Identifier,
     assignment: HashMap<String, Assignment>,
 }
 
+/// Settings shared by every assignment, from the optional `[defaults]` table.
+#[derive(Clone, Deserialize, Serialize, Debug, PartialEq, Default)]
+pub struct Defaults {
+    /// Options added to the options of every include. An option which takes a value, such as
+    /// `compression=<method>`, is overridden by an include giving the same option.
+    #[serde(default)]
+    options: Vec<String>,
+    /// Gitignore-style patterns of the entries to leave out of every directory include, in
+    /// addition to those in `.kerchiefignore`.
+    #[serde(default)]
+    exclude: Vec<String>,
+}
+
+impl Defaults {
+    pub fn options(&self) -> &[String] {
+        &self.options
+    }
+
+    pub fn exclude(&self) -> &[String] {
+        &self.exclude
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum FetchError {
-    #[error("The assignment key '{0}' is not present in the configuration.")]
-    NoSuchAssignmentKey(String),
+    #[error(
+        "The assignment key '{key}' is not present in the configuration. Available keys: {}.",
+        available.join(", ")
+    )]
+    NoSuchAssignmentKey { key: String, available: Vec<String> },
+}
+
+#[derive(Debug, Error)]
+pub enum TokenError {
+    #[error("both 'token' and 'token_file' are given, exactly one is expected")]
+    Both,
+    #[error("neither 'token' nor 'token_file' is given")]
+    Neither,
+    #[error("failed to read the token file '{path}': {source}")]
+    Read {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error(transparent)]
+    Home(#[from] HomeError),
+}
+
+#[derive(Debug, Error)]
+#[error("no 'domain' is given and the environment variable {DOMAIN_VAR} is not set")]
+pub struct MissingDomain;
+
+/// The environment variable holding the domain to use when the config gives none.
+pub const DOMAIN_VAR: &str = "KERCHIEF_DOMAIN";
+
+#[derive(Debug, Error)]
+pub enum UrlError {
+    #[error("'{url}' is not a valid url: {source}")]
+    Invalid {
+        url: String,
+        source: url::ParseError,
+    },
+    #[error("'{0}' is not a Canvas course url, expected a path like /courses/<id>")]
+    NotCourse(String),
+    #[error(
+        "'{0}' is not a Canvas assignment url, expected a path like /courses/<id>/assignments/<id>"
+    )]
+    NotAssignment(String),
+    #[error("in {place}: the url gives the id {url_id}, but the id {id} is given as well")]
+    Conflict { place: String, url_id: u64, id: u64 },
 }
 
 impl Config {
-    pub fn token(&self) -> &str {
-        &self.token
+    /// The token, either given inline or read (and trimmed) from the `token_file`. A leading
+    /// `~` in the path of the token file is expanded to the home directory.
+    pub fn resolve_token(&self) -> Result<String, TokenError> {
+        match (&self.token, &self.token_file) {
+            (Some(token), None) => Ok(token.clone()),
+            (None, Some(path)) => match fs::read_to_string(expand_home(path)?) {
+                Ok(token) => Ok(token.trim().to_owned()),
+                Err(source) => Err(TokenError::Read {
+                    path: path.clone(),
+                    source,
+                }),
+            },
+            (Some(_), Some(_)) => Err(TokenError::Both),
+            (None, None) => Err(TokenError::Neither),
+        }
     }
 
-    pub fn domain(&self) -> &str {
-        &self.domain
+    /// The domain given in the config or, failing that, by `env`, the value of the
+    /// `KERCHIEF_DOMAIN` environment variable if it is set.
+    pub fn resolve_domain(&self, env: Option<&str>) -> Result<String, MissingDomain> {
+        match &self.domain {
+            Some(domain) => Ok(domain.clone()),
+            None => env
+                .filter(|domain| !domain.is_empty())
+                .map(str::to_owned)
+                .ok_or(MissingDomain),
+        }
     }
 
     pub fn course_ident(&self) -> &Identifier {
         &self.course
     }
 
+    pub fn defaults(&self) -> &Defaults {
+        &self.defaults
+    }
+
     fn assignments(&self) -> impl Iterator<Item = (&str, &Assignment)> + '_ {
         self.assignment.iter().map(|(key, a)| (key.borrow(), a))
     }
 
+    /// Fill in the id of the course and of every assignment that is identified by a Canvas
+    /// `url`. An id given as well must agree with the url.
+    pub fn resolve_urls(&mut self) -> Result<(), UrlError> {
+        self.course
+            .resolve_url(course_url_id, || "the course".to_owned())?;
+        for (key, assignment) in self.assignment.iter_mut() {
+            assignment
+                .ident
+                .resolve_url(assignment_url_id, || format!("assignment '{}'", key))?;
+        }
+        Ok(())
+    }
+
+    /// The key of the assignment to use when none is given: the `default_assignment`, or the
+    /// only assignment if there is exactly one.
+    pub fn default_assignment(&self) -> Option<&str> {
+        match &self.default_assignment {
+            Some(key) => Some(key),
+            None if self.assignment.len() == 1 => self.assignment_keys().next(),
+            None => None,
+        }
+    }
+
+    /// The limit on the total size of the files of a submission, if one is given.
+    pub fn max_upload_bytes(&self) -> Option<u64> {
+        self.max_upload_bytes
+    }
+
+    /// The number of seconds after which the upload of a file is given up, if given.
+    pub fn upload_timeout_secs(&self) -> Option<u64> {
+        self.upload_timeout_secs
+    }
+
+    /// The number of items per page when fetching a list from Canvas, if given.
+    pub fn per_page(&self) -> Option<u32> {
+        self.per_page
+    }
+
+    /// The cap on the number of courses or assignments fetched from Canvas, if given.
+    pub fn max_items(&self) -> Option<usize> {
+        self.max_items
+    }
+
+    /// The IANA name of the timezone to show dates in, such as "Europe/Stockholm", if given.
+    pub fn timezone(&self) -> Option<&str> {
+        self.timezone.as_deref()
+    }
+
+    /// The keys of the assignments in the config, in sorted order.
+    pub fn assignment_keys(&self) -> impl Iterator<Item = &str> + '_ {
+        let mut keys: Vec<_> = self.assignments().map(|(key, _)| key).collect();
+        keys.sort_unstable();
+        keys.into_iter()
+    }
+
     pub fn assignment(&self, key: &str) -> Result<&Assignment, FetchError> {
         match self.assignments().find(|(k, _)| key == *k).map(|(_, a)| a) {
             Some(assignment) => Ok(assignment),
-            None => Err(FetchError::NoSuchAssignmentKey(key.to_owned())),
+            None => Err(FetchError::NoSuchAssignmentKey {
+                key: key.to_owned(),
+                available: self.assignment_keys().map(str::to_owned).collect(),
+            }),
         }
     }
 }
 
-#[derive(Clone, Deserialize, Debug, PartialEq, Default)]
+/// Serialize a secret as `<redacted>`, so that it never leaks into printed output.
+fn redact<S: Serializer>(secret: &Option<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    match secret {
+        Some(_) => serializer.serialize_str("<redacted>"),
+        None => serializer.serialize_none(),
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq, Hash, Clone)]
+pub enum HomeError {
+    #[error("'{0}' names the home directory of another user, only '~' and '~/' are expanded")]
+    OtherUser(String),
+    #[error("'{0}' starts with '~', but the environment variable HOME is not set")]
+    NoHome(String),
+}
+
+/// Expand a leading `~` or `~/` in `path` to the home directory given by `HOME`. The home
+/// directory of another user, as in `~user/`, is not looked up but refused.
+pub fn expand_home(path: &str) -> Result<PathBuf, HomeError> {
+    let tilde_rest = match path.strip_prefix('~') {
+        Some(tilde_rest) => tilde_rest,
+        None => return Ok(PathBuf::from(path)),
+    };
+    let rest = match tilde_rest.strip_prefix('/') {
+        Some(rest) => rest,
+        None if tilde_rest.is_empty() => tilde_rest,
+        None => return Err(HomeError::OtherUser(path.to_owned())),
+    };
+    match env::var_os("HOME") {
+        Some(home) => Ok(PathBuf::from(home).join(rest)),
+        None => Err(HomeError::NoHome(path.to_owned())),
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug, PartialEq, Default)]
 pub struct Identifier {
     name: Option<String>,
     id: Option<u64>,
+    url: Option<String>,
+}
+
+/// A numeric string identifies by id, anything else by name.
+impl From<&str> for Identifier {
+    fn from(s: &str) -> Self {
+        match s.parse() {
+            Ok(id) => Identifier {
+                id: Some(id),
+                ..Default::default()
+            },
+            Err(_) => Identifier {
+                name: Some(s.to_owned()),
+                ..Default::default()
+            },
+        }
+    }
 }
 
 pub enum ReadIdentifier<'read> {
@@ -55,44 +267,134 @@ pub enum ReadIdentifier<'read> {
     None,
 }
 
+/// The path segments of `url`, without empty ones such as from a trailing slash.
+fn url_segments(url: &str) -> Result<Vec<String>, UrlError> {
+    let parsed = Url::parse(url).map_err(|source| UrlError::Invalid {
+        url: url.to_owned(),
+        source,
+    })?;
+    Ok(parsed
+        .path_segments()
+        .map(|segments| {
+            segments
+                .filter(|s| !s.is_empty())
+                .map(str::to_owned)
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// The course id of a url to a course page, `/courses/<id>`, or to any page within it.
+fn course_url_id(url: &str) -> Result<u64, UrlError> {
+    match url_segments(url)?.as_slice() {
+        [courses, id, ..] if courses == "courses" => {
+            id.parse().map_err(|_| UrlError::NotCourse(url.to_owned()))
+        }
+        _ => Err(UrlError::NotCourse(url.to_owned())),
+    }
+}
+
+/// The assignment id of a url to an assignment page, `/courses/<id>/assignments/<id>`.
+fn assignment_url_id(url: &str) -> Result<u64, UrlError> {
+    match url_segments(url)?.as_slice() {
+        [courses, course_id, assignments, id]
+            if courses == "courses"
+                && assignments == "assignments"
+                && course_id.parse::<u64>().is_ok() =>
+        {
+            id.parse()
+                .map_err(|_| UrlError::NotAssignment(url.to_owned()))
+        }
+        _ => Err(UrlError::NotAssignment(url.to_owned())),
+    }
+}
+
 impl Identifier {
+    /// An identifier by `name`, by `id` or by both.
+    pub fn new(name: Option<String>, id: Option<u64>) -> Self {
+        Identifier {
+            name,
+            id,
+            ..Default::default()
+        }
+    }
+
+    /// An identifier by the id in a Canvas assignment url, such as
+    /// `https://example.instructure.com/courses/1234/assignments/5678`.
+    pub fn from_canvas_url(url: &str) -> Result<Self, UrlError> {
+        Ok(Identifier {
+            id: Some(assignment_url_id(url)?),
+            url: Some(url.to_owned()),
+            ..Default::default()
+        })
+    }
+
+    /// Fill in the id from the url, as read by `url_id`. The `place` of the identifier names it
+    /// in an error.
+    fn resolve_url(
+        &mut self,
+        url_id: fn(&str) -> Result<u64, UrlError>,
+        place: impl FnOnce() -> String,
+    ) -> Result<(), UrlError> {
+        if let Some(url) = &self.url {
+            let url_id = url_id(url)?;
+            match self.id {
+                Some(id) if id != url_id => {
+                    return Err(UrlError::Conflict {
+                        place: place(),
+                        url_id,
+                        id,
+                    })
+                }
+                _ => self.id = Some(url_id),
+            }
+        }
+        Ok(())
+    }
+
     pub fn is_none(&self) -> bool {
-        self.name.is_none() && self.name.is_none()
+        self.as_name_id() == (None, None)
+    }
+
+    pub fn get_name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// The id, either given or read from the url by `Config::resolve_urls`.
+    pub fn get_id(&self) -> Option<u64> {
+        self.id
+    }
+
+    /// The name and the id, as given. A url has been read into the id by `Config::resolve_urls`.
+    pub fn as_name_id(&self) -> (Option<&str>, Option<u64>) {
+        (self.get_name(), self.get_id())
     }
 
     pub fn read(&self) -> ReadIdentifier<'_> {
-        match self {
-            Identifier {
-                name: Some(name),
-                id: Some(id),
-            } => {
-                let id = *id;
-                ReadIdentifier::NameAndId { name, id }
-            }
-            Identifier {
-                name: Some(name),
-                id: None,
-            } => ReadIdentifier::NameOnly { name },
-            Identifier {
-                name: None,
-                id: Some(id),
-            } => {
-                let id = *id;
-                ReadIdentifier::IdOnly { id }
-            }
-            Identifier {
-                name: None,
-                id: None,
-            } => ReadIdentifier::None,
+        ReadIdentifier::from(self.as_name_id())
+    }
+}
+
+impl<'read> From<(Option<&'read str>, Option<u64>)> for ReadIdentifier<'read> {
+    fn from(name_id: (Option<&'read str>, Option<u64>)) -> Self {
+        match name_id {
+            (Some(name), Some(id)) => ReadIdentifier::NameAndId { name, id },
+            (Some(name), None) => ReadIdentifier::NameOnly { name },
+            (None, Some(id)) => ReadIdentifier::IdOnly { id },
+            (None, None) => ReadIdentifier::None,
         }
     }
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Clone, Deserialize, Serialize, Debug, PartialEq)]
 pub struct Assignment {
     #[serde(flatten)]
     ident: Identifier,
     include: Include,
+    bundle: Option<String>,
+    note: Option<String>,
+    pre_submit: Option<String>,
+    post_submit: Option<String>,
 }
 
 impl Assignment {
@@ -100,6 +402,37 @@ impl Assignment {
         &self.ident
     }
 
+    /// The name of the assignment on Canvas, if it is identified by name.
+    pub fn get_name(&self) -> Option<&str> {
+        self.ident.get_name()
+    }
+
+    /// The id of the assignment on Canvas, if it is identified by id or url.
+    pub fn get_id(&self) -> Option<u64> {
+        self.ident.get_id()
+    }
+
+    /// The file name of a single zip archive to bundle all of the includes into, instead of
+    /// uploading each of them separately.
+    pub fn bundle(&self) -> Option<&str> {
+        self.bundle.as_deref()
+    }
+
+    /// A free-text reminder for the user, local to the config and never sent to Canvas.
+    pub fn note(&self) -> Option<&str> {
+        self.note.as_deref()
+    }
+
+    /// A shell command to run before the includes are staged. If it fails, nothing is submitted.
+    pub fn pre_submit(&self) -> Option<&str> {
+        self.pre_submit.as_deref()
+    }
+
+    /// A shell command to run after a successful submission.
+    pub fn post_submit(&self) -> Option<&str> {
+        self.post_submit.as_deref()
+    }
+
     pub fn include(&self) -> Vec<&Path> {
         match &self.include {
             Include::Single(path) => vec![path],
@@ -108,7 +441,7 @@ impl Assignment {
     }
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Clone, Deserialize, Serialize, Debug, PartialEq)]
 #[serde(untagged)]
 pub enum Path {
     Flat(String),
@@ -120,11 +453,46 @@ pub enum Path {
 
 use std::collections::HashSet;
 
+/// A path followed by a colon and comma separated options, as in `dir:zip,compression=stored`,
+/// or a path alone. Everything after the first colon counts as options, except for the colon
+/// of a leading drive such as `C:\`.
+impl From<&str> for Path {
+    fn from(s: &str) -> Self {
+        let drive_len = match s.as_bytes() {
+            [letter, b':', b'\\' | b'/', ..] if letter.is_ascii_alphabetic() => 2,
+            _ => 0,
+        };
+        match s[drive_len..].split_once(':') {
+            Some((path, options)) => Path::Optioned {
+                path: format!("{}{}", &s[..drive_len], path),
+                options: Some(options.split(',').map(str::to_owned).collect()),
+            },
+            None => Path::Flat(s.to_owned()),
+        }
+    }
+}
+
 impl Path {
     pub fn path(&self) -> &str {
         match self {
-            Self::Flat(path) => &path,
-            Self::Optioned { path, .. } => &path,
+            Self::Flat(path) => path,
+            Self::Optioned { path, .. } => path,
+        }
+    }
+
+    /// This path resolved against the directory `dir` rather than the kerchief root, keeping its
+    /// options. An absolute path, or one starting from the home directory, is left unchanged.
+    pub fn relative_to(&self, dir: &std::path::Path) -> Path {
+        let path = match self.path() {
+            path if path.starts_with('~') => path.to_owned(),
+            path => dir.join(path).to_string_lossy().into_owned(),
+        };
+        match self {
+            Self::Flat(_) => Self::Flat(path),
+            Self::Optioned { options, .. } => Self::Optioned {
+                path,
+                options: options.clone(),
+            },
         }
     }
 
@@ -133,15 +501,14 @@ impl Path {
             Self::Flat(_) => HashSet::new(),
             Self::Optioned { options, .. } => options
                 .iter()
-                .map(|s| s.iter())
-                .flatten()
+                .flat_map(|s| s.iter())
                 .map(String::as_ref)
                 .collect(),
         }
     }
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Clone, Deserialize, Serialize, Debug, PartialEq)]
 #[serde(untagged)]
 enum Include {
     Single(Path),
@@ -150,16 +517,27 @@ enum Include {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     #[test]
     fn simple_parsing_example() {
         let s = String::from;
 
         let mut config = Config {
-            token: s("1234"),
-            domain: s("uppsala.instructure.com"),
+            token: Some(s("1234")),
+            token_file: None,
+            domain: Some(s("uppsala.instructure.com")),
+            default_assignment: None,
+            max_upload_bytes: None,
+            upload_timeout_secs: None,
+            per_page: None,
+            max_items: None,
+            timezone: None,
+            defaults: Defaults::default(),
             course: Identifier {
                 name: Some(s("Datorgrafik")),
                 id: None,
+                url: None,
             },
             assignment: HashMap::new(),
         };
@@ -170,6 +548,7 @@ mod tests {
                 ident: Identifier {
                     name: Some(s("Assignment 1")),
                     id: None,
+                    url: None,
                 },
                 include: Include::Many(vec![
                     Path::Flat(s("group.txt")),
@@ -178,6 +557,10 @@ mod tests {
                         options: Some(vec![s("zip")]),
                     },
                 ]),
+                bundle: None,
+                note: Some(s("the lab report and the code")),
+                pre_submit: Some(s("make report")),
+                post_submit: None,
             },
         );
 
@@ -194,10 +577,225 @@ name = "Datorgrafik"
 name = "Assignment 1"
 include = [ "group.txt",
             { path = "assignment1", options = ["zip"] }, ]
+note = "the lab report and the code"
+pre_submit = "make report"
 "##,
         )
         .expect("ought to be valid toml");
 
         assert_eq!(parse_toml, config);
     }
+
+    #[test]
+    fn serialization_redacts_the_token() {
+        let source = r##"
+token = "1234"
+domain = "uppsala.instructure.com"
+
+[course]
+name = "Datorgrafik"
+
+[assignment.1]
+name = "Assignment 1"
+include = [ "group.txt", { path = "assignment1", options = ["zip"] } ]
+"##;
+        let config: Config = toml::from_str(source).expect("ought to be valid toml");
+        let shown = toml::to_string(&config).unwrap();
+        assert!(!shown.contains("1234"));
+
+        let mut reparsed: Config = toml::from_str(&shown).expect("ought to be valid toml");
+        assert_eq!(reparsed.token.as_deref(), Some("<redacted>"));
+        reparsed.token = config.token.clone();
+        assert_eq!(reparsed, config);
+    }
+
+    #[test]
+    fn home_expansion() {
+        let home = PathBuf::from(env::var_os("HOME").expect("HOME ought to be set"));
+        assert_eq!(expand_home("~/a/b.txt"), Ok(home.join("a/b.txt")));
+        assert_eq!(expand_home("~"), Ok(home));
+        assert_eq!(expand_home("a/~/b.txt"), Ok(PathBuf::from("a/~/b.txt")));
+        assert_eq!(
+            expand_home("~root/b.txt"),
+            Err(HomeError::OtherUser(String::from("~root/b.txt")))
+        );
+    }
+
+    #[test]
+    fn token_sources() {
+        let parse = |token_lines: &str| -> Config {
+            toml::from_str(&format!(
+                "{}\ndomain = \"d\"\n[course]\n[assignment]\n",
+                token_lines
+            ))
+            .expect("ought to be valid toml")
+        };
+
+        let token_file = env::temp_dir().join(format!("kerchief-token-{}", std::process::id()));
+        fs::write(&token_file, "5678\n").unwrap();
+        let from_file = parse(&format!("token_file = {:?}", token_file));
+        let both = parse(&format!("token = \"1234\"\ntoken_file = {:?}", token_file));
+        let from_file = from_file.resolve_token();
+        fs::remove_file(&token_file).unwrap();
+
+        assert_eq!(parse("token = \"1234\"").resolve_token().unwrap(), "1234");
+        assert_eq!(from_file.unwrap(), "5678");
+        assert!(matches!(both.resolve_token(), Err(TokenError::Both)));
+        assert!(matches!(
+            parse("").resolve_token(),
+            Err(TokenError::Neither)
+        ));
+    }
+
+    #[test]
+    fn unknown_assignment_key() {
+        let config: Config = toml::from_str(
+            "token = \"1234\"\ndomain = \"d\"\n[course]\n\
+             [assignment.lab2]\ninclude = \"b.txt\"\n[assignment.lab1]\ninclude = \"a.txt\"\n",
+        )
+        .expect("ought to be valid toml");
+        assert_eq!(
+            config.assignment_keys().collect::<Vec<_>>(),
+            ["lab1", "lab2"]
+        );
+        assert_eq!(
+            config.assignment("lab3").unwrap_err().to_string(),
+            "The assignment key 'lab3' is not present in the configuration. \
+             Available keys: lab1, lab2."
+        );
+    }
+
+    #[test]
+    fn default_assignment() {
+        let parse = |s: &str| -> Config {
+            toml::from_str(&format!("token = \"1234\"\ndomain = \"d\"\n{}", s))
+                .expect("ought to be valid toml")
+        };
+        let lone = parse("[course]\n[assignment.lab1]\ninclude = \"a.txt\"\n");
+        assert_eq!(lone.default_assignment(), Some("lab1"));
+        let explicit = parse(
+            "default_assignment = \"lab2\"\n[course]\n\
+             [assignment.lab1]\ninclude = \"a.txt\"\n[assignment.lab2]\ninclude = \"b.txt\"\n",
+        );
+        assert_eq!(explicit.default_assignment(), Some("lab2"));
+    }
+
+    #[test]
+    fn domain_sources() {
+        let config: Config = toml::from_str("token = \"1234\"\n[course]\n[assignment]\n")
+            .expect("ought to be valid toml");
+        assert!(config.resolve_domain(None).is_err());
+        assert!(config.resolve_domain(Some("")).is_err());
+        assert_eq!(
+            config
+                .resolve_domain(Some("example.instructure.com"))
+                .unwrap(),
+            "example.instructure.com"
+        );
+        let config: Config = toml::from_str(
+            "token = \"1234\"\ndomain = \"canvas.example.edu\"\n[course]\n[assignment]\n",
+        )
+        .expect("ought to be valid toml");
+        assert_eq!(
+            config
+                .resolve_domain(Some("example.instructure.com"))
+                .unwrap(),
+            "canvas.example.edu"
+        );
+    }
+
+    #[test]
+    fn identifier_name_and_id() {
+        let both = Identifier::new(Some(String::from("Lab 1")), Some(5));
+        assert_eq!(both.as_name_id(), (Some("Lab 1"), Some(5)));
+        assert_eq!(both.get_name(), Some("Lab 1"));
+        assert_eq!(both.get_id(), Some(5));
+        assert!(matches!(
+            both.read(),
+            ReadIdentifier::NameAndId {
+                name: "Lab 1",
+                id: 5
+            }
+        ));
+        assert_eq!(Identifier::from("5").as_name_id(), (None, Some(5)));
+        assert_eq!(
+            Identifier::from("Lab 1").as_name_id(),
+            (Some("Lab 1"), None)
+        );
+        assert!(!Identifier::from("5").is_none());
+        assert!(Identifier::default().is_none());
+        assert!(matches!(Identifier::default().read(), ReadIdentifier::None));
+    }
+
+    #[test]
+    fn canvas_urls() {
+        let url = "https://example.instructure.com/courses/1234/assignments/5678";
+        assert!(matches!(
+            Identifier::from_canvas_url(url).unwrap().read(),
+            ReadIdentifier::IdOnly { id: 5678 }
+        ));
+        assert_eq!(assignment_url_id(&format!("{}/", url)).unwrap(), 5678);
+        assert_eq!(
+            course_url_id("https://example.instructure.com/courses/1234").unwrap(),
+            1234
+        );
+        assert!(matches!(
+            Identifier::from_canvas_url("https://example.instructure.com/courses/1234"),
+            Err(UrlError::NotAssignment(_))
+        ));
+        assert!(matches!(
+            Identifier::from_canvas_url("not a url"),
+            Err(UrlError::Invalid { .. })
+        ));
+
+        let mut config: Config = toml::from_str(&format!(
+            "token = \"1234\"\ndomain = \"d\"\n[course]\nurl = \"https://d/courses/1234\"\n\
+             [assignment.1]\nurl = \"{}\"\ninclude = \"a.txt\"\n",
+            url
+        ))
+        .expect("ought to be valid toml");
+        config.resolve_urls().unwrap();
+        assert!(matches!(
+            config.course_ident().read(),
+            ReadIdentifier::IdOnly { id: 1234 }
+        ));
+        assert!(matches!(
+            config.assignment("1").unwrap().ident().read(),
+            ReadIdentifier::IdOnly { id: 5678 }
+        ));
+        assert_eq!(config.assignment("1").unwrap().get_id(), Some(5678));
+        assert_eq!(config.assignment("1").unwrap().get_name(), None);
+
+        let mut conflicting: Config = toml::from_str(&format!(
+            "token = \"1234\"\ndomain = \"d\"\n[course]\n\
+             [assignment.lab]\nurl = \"{}\"\nid = 1\ninclude = \"a.txt\"\n",
+            url
+        ))
+        .expect("ought to be valid toml");
+        assert_eq!(
+            conflicting.resolve_urls().unwrap_err().to_string(),
+            "in assignment 'lab': the url gives the id 5678, but the id 1 is given as well"
+        );
+    }
+
+    #[test]
+    fn paths_from_flags() {
+        let s = String::from;
+        assert_eq!(Path::from("notes.txt"), Path::Flat(s("notes.txt")));
+        assert_eq!(
+            Path::from("dir:zip,compression=deflate:9"),
+            Path::Optioned {
+                path: s("dir"),
+                options: Some(vec![s("zip"), s("compression=deflate:9")]),
+            }
+        );
+        assert_eq!(Path::from(r"C:\work\lab1"), Path::Flat(s(r"C:\work\lab1")));
+        assert_eq!(
+            Path::from("C:/work/lab1:zip,compression=deflate:9"),
+            Path::Optioned {
+                path: s("C:/work/lab1"),
+                options: Some(vec![s("zip"), s("compression=deflate:9")]),
+            }
+        );
+    }
 }
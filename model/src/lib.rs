@@ -1,18 +1,99 @@
-use once_cell::unsync::OnceCell;
+use chrono::{DateTime, FixedOffset, Local};
+use chrono_tz::Tz;
+use once_cell::sync::OnceCell;
+use std::collections::HashSet;
 use std::convert::TryFrom;
 use std::io::Read;
-use std::{fmt, fs, io, path};
+use std::{env, fmt, fs, io, path, time};
 use thiserror::Error;
 
+mod pending;
+mod stage;
+pub use stage::{
+    run_hook, Payload, Receipt, StageError, StagingDir, SubmitOptions, SubmitPrompts, Unattended,
+};
+
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub enum FileOption {
     Zip,
+    /// The compression used for the entries of a zip archive, written as
+    /// `compression=stored`, `compression=deflate` or `compression=deflate:<level>`.
+    Compression(Compression),
+    /// Leave out the entries of a directory which are ignored by the project's `.gitignore`
+    /// files.
+    RespectGitignore,
+    /// The content type to upload the file as, written as `content_type=<mime type>`.
+    ContentType(String),
+    /// Allow the path to lie outside the kerchief root, e.g. in the home directory.
+    OutsideRoot,
+}
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum Compression {
+    Stored,
+    /// Deflate with the given level between 0 and 9, or the default level.
+    Deflate(Option<u8>),
 }
 
 #[derive(Clone, Debug, Error)]
 pub enum FileOptionError {
-    #[error("{0}")]
+    #[error("unknown option '{0}'")]
     Unexpected(String),
+    #[error("invalid compression '{0}', expected 'stored', 'deflate' or 'deflate:<level 0-9>'")]
+    Compression(String),
+    #[error("conflicting options '{0}' and '{1}'")]
+    Conflict(String, String),
+    #[error("the option '{0}' only applies together with 'zip'")]
+    Unzipped(String),
+    #[error("the option '{0}' does not apply to a zipped directory")]
+    ZippedDir(String),
+}
+
+impl FileOption {
+    /// Parse the options of an include, each either a flag such as `zip` or a `key=value`
+    /// option such as `compression=stored`. A key may only be given once.
+    pub fn parse_all<'a>(
+        opts: impl IntoIterator<Item = &'a str>,
+    ) -> Result<HashSet<FileOption>, FileOptionError> {
+        let mut parsed = HashSet::new();
+        let mut valued: Vec<(&str, &str)> = Vec::new();
+        for opt in opts {
+            if let Some((key, _)) = opt.split_once('=') {
+                if let Some((_, earlier)) = valued.iter().find(|(earlier, _)| *earlier == key) {
+                    return Err(FileOptionError::Conflict(
+                        (*earlier).to_owned(),
+                        opt.to_owned(),
+                    ));
+                }
+                valued.push((key, opt));
+            }
+            parsed.insert(FileOption::try_from(opt)?);
+        }
+        Ok(parsed)
+    }
+}
+
+/// Fail on an option which staging the include at `path` would have no use for: a
+/// `compression` without `zip`, unless the include is `bundled` into an archive anyway, or a
+/// `content_type` of a zipped directory, which would name the type of the archive.
+fn check_zip_options(
+    path: &IncludePath,
+    options: HashSet<FileOption>,
+    bundled: bool,
+) -> Result<HashSet<FileOption>, FileOptionError> {
+    let zipped = options.contains(&FileOption::Zip);
+    for option in &options {
+        match option {
+            FileOption::Compression(_) if !zipped && !bundled => {
+                return Err(FileOptionError::Unzipped(option.to_string()));
+            }
+            FileOption::ContentType(_) if zipped && matches!(path, IncludePath::Dir(_)) => {
+                return Err(FileOptionError::ZippedDir(option.to_string()));
+            }
+            _ => {}
+        }
+    }
+    Ok(options)
 }
 
 impl TryFrom<&str> for FileOption {
@@ -20,19 +101,55 @@ impl TryFrom<&str> for FileOption {
     fn try_from(string: &str) -> Result<Self, Self::Error> {
         if string == "zip" {
             Ok(FileOption::Zip)
+        } else if string == "respect_gitignore" {
+            Ok(FileOption::RespectGitignore)
+        } else if string == "outside_root" {
+            Ok(FileOption::OutsideRoot)
+        } else if let Some(content_type) = string.strip_prefix("content_type=") {
+            Ok(FileOption::ContentType(content_type.to_owned()))
+        } else if let Some(compression) = string.strip_prefix("compression=") {
+            Ok(FileOption::Compression(Compression::try_from(compression)?))
         } else {
             Err(FileOptionError::Unexpected(string.to_owned()))
         }
     }
 }
 
+impl TryFrom<&str> for Compression {
+    type Error = FileOptionError;
+    fn try_from(string: &str) -> Result<Self, Self::Error> {
+        let invalid = || FileOptionError::Compression(string.to_owned());
+        match string.split_once(':') {
+            None if string == "stored" => Ok(Compression::Stored),
+            None if string == "deflate" => Ok(Compression::Deflate(None)),
+            Some(("deflate", level)) => match level.parse() {
+                Ok(level) if level <= 9 => Ok(Compression::Deflate(Some(level))),
+                _ => Err(invalid()),
+            },
+            _ => Err(invalid()),
+        }
+    }
+}
+
 impl fmt::Display for FileOption {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let display = match &self {
-            FileOption::Zip => "zip",
-        };
+        match &self {
+            FileOption::Zip => write!(f, "zip"),
+            FileOption::RespectGitignore => write!(f, "respect_gitignore"),
+            FileOption::OutsideRoot => write!(f, "outside_root"),
+            FileOption::ContentType(content_type) => write!(f, "content_type={}", content_type),
+            FileOption::Compression(compression) => write!(f, "compression={}", compression),
+        }
+    }
+}
 
-        write!(f, "{}", display)
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self {
+            Compression::Stored => write!(f, "stored"),
+            Compression::Deflate(None) => write!(f, "deflate"),
+            Compression::Deflate(Some(level)) => write!(f, "deflate:{}", level),
+        }
     }
 }
 
@@ -136,8 +253,9 @@ impl<T> From<(u64, String, T)> for OwnedIdentifier {
 }
 
 /// A string which points to the path of a file or directory during the point
-/// of construction. A relative path is evaluated relative to the root
-/// 'kerchief.toml'.
+/// of construction. A relative path is evaluated relative to the directory of the
+/// root 'kerchief.toml', regardless of the current working directory. A leading `~`
+/// is expanded to the home directory.
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub enum IncludePath {
     File(path::PathBuf),
@@ -148,19 +266,37 @@ pub enum IncludePath {
 pub enum IncludeError {
     #[error("path {0} not found")]
     NotPresent(path::PathBuf),
+    #[error(
+        "path {0} lies outside the kerchief root, add the option 'outside_root' to include it"
+    )]
+    OutsideRoot(path::PathBuf),
+    #[error(transparent)]
+    Home(#[from] config::HomeError),
 }
 
 impl IncludePath {
-    fn try_find(path: &str) -> Result<Self, IncludeError> {
-        let path = path::Path::new(path);
+    /// Resolve `path` relative to `root`. Unless `outside_root` is set, a path leading out of
+    /// the root, by `..`, an absolute path or a symbolic link, is refused.
+    fn try_find(root: &path::Path, path: &str, outside_root: bool) -> Result<Self, IncludeError> {
+        let path = root.join(config::expand_home(path)?);
 
-        if path.is_file() {
-            Ok(Self::File(path.to_owned()))
+        let include = if path.is_file() {
+            Self::File(path.to_owned())
         } else if path.is_dir() {
-            Ok(Self::Dir(path.to_owned()))
+            Self::Dir(path.to_owned())
         } else {
-            Err(IncludeError::NotPresent(path.to_owned()))
+            return Err(IncludeError::NotPresent(path.to_owned()));
+        };
+        if !outside_root {
+            let within_root = match (path.canonicalize(), root.canonicalize()) {
+                (Ok(path), Ok(root)) => path.starts_with(root),
+                _ => false,
+            };
+            if !within_root {
+                return Err(IncludeError::OutsideRoot(path.to_owned()));
+            }
         }
+        Ok(include)
     }
 
     pub fn path(&self) -> &path::Path {
@@ -168,17 +304,11 @@ impl IncludePath {
     }
 
     pub fn is_file(&self) -> bool {
-        match self {
-            Self::File(_) => true,
-            _ => false,
-        }
+        matches!(self, Self::File(_))
     }
 
     pub fn is_dir(&self) -> bool {
-        match self {
-            Self::Dir(_) => true,
-            _ => false,
-        }
+        matches!(self, Self::Dir(_))
     }
 }
 
@@ -197,6 +327,19 @@ impl AsRef<path::Path> for IncludePath {
     }
 }
 
+/// An include entry of an assignment, with its path resolved and its options parsed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StagedInclude {
+    pub path: IncludePath,
+    pub options: HashSet<FileOption>,
+}
+
+/// An include path as resolved from the config, paired with its parsed options.
+pub type IncludeEntry = (
+    Result<IncludePath, IncludeError>,
+    Result<HashSet<FileOption>, FileOptionError>,
+);
+
 /// As the path is created from a string, we can unwrap it as a string.
 impl<'a> From<&'a IncludePath> for &'a str {
     fn from(p: &'a IncludePath) -> &'a str {
@@ -212,10 +355,96 @@ pub enum BuildError {
     Ident(#[from] IdentifierError),
     #[error("course identifier error: {0}")]
     Iden(#[from] IdentifierErr),
+    #[error("in assignment '{key}': {source}")]
+    AssignmentIdent { key: String, source: IdentifierErr },
+    #[error("no assignments found for this course")]
+    NoAssignments,
+    #[error("the assignment '{0}' is not yet open")]
+    NotYetOpen(String),
+    #[error("the assignment '{0}' is locked")]
+    Locked(String),
+    #[error(transparent)]
+    Stage(#[from] StageError),
+    #[error(transparent)]
+    Token(#[from] config::TokenError),
+    #[error(transparent)]
+    Domain(#[from] config::MissingDomain),
+    #[error("assignment '{key}' has no attempt {attempt}, there are {attempts}")]
+    NoSuchAttempt {
+        key: String,
+        attempt: u64,
+        attempts: usize,
+    },
     #[error("canvas api error: {0}")]
     Canvas(#[from] canvas::Error),
     #[error(transparent)]
     Fetch(#[from] config::FetchError),
+    #[error("invalid assignment key pattern: {0}")]
+    Pattern(#[from] globset::Error),
+    #[error("no assignment key matches '{0}'")]
+    NoMatch(String),
+    #[error("in assignment '{key}': {source}")]
+    Include { key: String, source: IncludeError },
+    #[error("in assignment '{key}': {source}")]
+    Option {
+        key: String,
+        source: FileOptionError,
+    },
+}
+
+/// An assignment together with the latest submission to it, answering whether it is
+/// submitted, locked or overdue.
+#[derive(Clone, Debug)]
+pub struct AssignmentStatus {
+    assignment: canvas::Assignment,
+    submission: Option<canvas::Submission>,
+}
+
+impl AssignmentStatus {
+    /// The status of `assignment`, where `submission` is the latest submission to it, if any.
+    pub fn new(assignment: canvas::Assignment, submission: Option<canvas::Submission>) -> Self {
+        Self {
+            assignment,
+            submission: submission.filter(canvas::Submission::is_submitted),
+        }
+    }
+
+    pub fn assignment(&self) -> &canvas::Assignment {
+        &self.assignment
+    }
+
+    /// The latest submission, if anything has been submitted.
+    pub fn submission(&self) -> Option<&canvas::Submission> {
+        self.submission.as_ref()
+    }
+
+    pub fn is_submitted(&self) -> bool {
+        self.submission.is_some()
+    }
+
+    pub fn is_locked(&self, now: &DateTime<Local>) -> bool {
+        self.assignment.is_locked(now)
+    }
+
+    /// Whether the assignment has been unlocked at the time `now`, before which Canvas refuses
+    /// submissions.
+    pub fn is_open(&self, now: &DateTime<Local>) -> bool {
+        self.assignment.is_open(now)
+    }
+
+    /// Whether the due date has passed without anything having been submitted.
+    pub fn is_overdue(&self, now: &DateTime<Local>) -> bool {
+        !self.is_submitted() && self.assignment.due_at().is_some_and(|due_at| due_at < now)
+    }
+
+    /// Whether the assignment is due, or was last submitted to, at or after `since`.
+    pub fn is_since(&self, since: &DateTime<Local>) -> bool {
+        let submitted_at = self.submission().and_then(canvas::Submission::submitted_at);
+        self.assignment
+            .due_at()
+            .is_some_and(|due_at| due_at >= since)
+            || submitted_at.is_some_and(|submitted_at| submitted_at >= since)
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -293,12 +522,12 @@ impl<'a> Identifier<'a> {
                     if ident.id == *id {
                         id_matches.push(ident);
                     }
-                    if ident.name == *name {
+                    if names_match(ident.name, name) {
                         name_matches.push(ident);
                     }
                 }
 
-                if let Some(&id_match) = id_matches.get(0) {
+                if let Some(&id_match) = id_matches.first() {
                     if name_matches.contains(&id_match) {
                         Ok(id_match)
                     } else {
@@ -355,12 +584,12 @@ impl<'a> Identifier<'a> {
             config::ReadIdentifier::NameOnly { name } => {
                 let name_matches: Vec<Self> = matches
                     .iter()
-                    .filter(|ident| ident.name == *name)
+                    .filter(|ident| names_match(ident.name, name))
                     .cloned()
                     .collect();
-                if let Some(&name_match) = name_matches.get(0) {
+                if let Some(&name_match) = name_matches.first() {
                     if name_matches.len() == 1 {
-                        Ok(name_match.clone())
+                        Ok(name_match)
                     } else {
                         Err(IdentifierErr::UnderSpecified {
                             user_provided: (*name).to_owned(),
@@ -381,6 +610,33 @@ impl<'a> Identifier<'a> {
     }
 }
 
+/// The `options` of an include merged with the `defaults` from the config. Every default is
+/// added, except one which takes a value, e.g. `compression=stored`, when the include gives the
+/// same option with a value of its own.
+fn with_defaults<'a>(options: HashSet<&'a str>, defaults: &'a [String]) -> HashSet<&'a str> {
+    let own_names: HashSet<_> = options
+        .iter()
+        .filter_map(|option| option.split_once('='))
+        .map(|(name, _)| name)
+        .collect();
+    let mut merged = options;
+    for default in defaults {
+        match default.split_once('=') {
+            Some((name, _)) if own_names.contains(name) => (),
+            _ => {
+                merged.insert(default);
+            }
+        }
+    }
+    merged
+}
+
+/// Whether the names `a` and `b` are equal up to whitespace: leading and trailing whitespace is
+/// ignored, and runs of whitespace, non-breaking spaces included, compare equal.
+fn names_match(a: &str, b: &str) -> bool {
+    a.split_whitespace().eq(b.split_whitespace())
+}
+
 impl<'a> From<&'a canvas::Course> for Identifier<'a> {
     fn from(course: &'a canvas::Course) -> Self {
         Self {
@@ -399,61 +655,234 @@ impl<'a> From<&'a canvas::Assignment> for Identifier<'a> {
     }
 }
 
+/// The config together with what has been fetched from Canvas for it. The courses and the
+/// assignments are fetched at most once, when first asked for, and then kept.
+///
+/// A `Wall` is `Send` and `Sync`, so it may be shared between threads, e.g. in an `Arc`. If two
+/// threads ask for something not yet fetched at the same time, one fetches it while the other
+/// waits for the result. A clone keeps what has been fetched so far, but fetches anything else
+/// on its own.
+#[derive(Clone)]
 pub struct Wall {
     user_cfg: config::Config,
+    root: path::PathBuf,
+    credentials: OnceCell<(String, String)>,
+    timezone: Option<Tz>,
+    course_override: Option<config::Identifier>,
+    extra_includes: Vec<config::Path>,
     courses: OnceCell<Vec<canvas::Course>>,
     assignments: OnceCell<Vec<canvas::Assignment>>,
 }
 
+/// The names a config file may have, the format following from the extension. The first one
+/// is the default.
+pub const CONFIG_FILE_NAMES: [&str; 4] = [
+    "kerchief.toml",
+    "kerchief.json",
+    "kerchief.yaml",
+    "kerchief.yml",
+];
+
+/// Parse the config `source`, read from `p`, as JSON or YAML by the extension of `p` and as TOML
+/// otherwise.
+fn parse_config(p: &path::Path, source: &str) -> Result<config::Config, ParseError> {
+    match p.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => Ok(serde_json::from_str(source)?),
+        Some("yaml") | Some("yml") => Ok(serde_yaml::from_str(source)?),
+        _ => Ok(toml::from_str(source)?),
+    }
+}
+
+/// The limit on the total size of a submission when the config gives none, 100 MB.
+pub const DEFAULT_MAX_UPLOAD_BYTES: u64 = 100 * 1024 * 1024;
+
 #[derive(Debug, Error)]
 pub enum ParseError {
     #[error(transparent)]
     Parse(#[from] toml::de::Error),
     #[error(transparent)]
+    ParseJson(#[from] serde_json::Error),
+    #[error(transparent)]
+    ParseYaml(#[from] serde_yaml::Error),
+    #[error(transparent)]
     Read(#[from] io::Error),
+    #[error(transparent)]
+    Token(#[from] config::TokenError),
+    #[error(transparent)]
+    Domain(#[from] config::MissingDomain),
+    #[error(transparent)]
+    Url(#[from] config::UrlError),
+    #[error("unknown timezone '{0}', expected an IANA name such as 'Europe/Stockholm'")]
+    Timezone(String),
+    #[error("in assignment '{key}': {source}")]
+    Option {
+        key: String,
+        source: FileOptionError,
+    },
+    #[error("in [defaults]: {0}")]
+    DefaultOption(FileOptionError),
 }
 
 impl Wall {
+    /// Read the config at `p`, in the format that its extension names. The directory containing
+    /// it becomes the root that relative include paths are resolved against.
     pub fn try_from_path<P: AsRef<path::Path>>(p: P) -> Result<Self, ParseError> {
+        let p = p.as_ref().canonicalize()?;
         let mut buf = String::new();
-        fs::File::open(p)?.read_to_string(&mut buf)?;
-        Ok(Self::new(toml::from_str(&buf)?))
+        fs::File::open(&p)?.read_to_string(&mut buf)?;
+        // a canonicalized path to a file always has a parent
+        let root = p.parent().unwrap().to_owned();
+        let wall = Self::new(parse_config(&p, &buf)?, root)?;
+        wall.validate_options()?;
+        Ok(wall)
     }
 
-    pub fn new(user_cfg: config::Config) -> Self {
-        Self {
+    /// Check that every include option in the config is a valid `FileOption`, so that a
+    /// misconfiguration is caught before any network activity.
+    pub fn validate_options(&self) -> Result<(), ParseError> {
+        let defaults = self.user_cfg.defaults().options();
+        FileOption::parse_all(defaults.iter().map(String::as_str))
+            .map_err(ParseError::DefaultOption)?;
+        for key in self.user_cfg.assignment_keys() {
+            // the key was listed by the config itself, so it is present
+            let assignment = self.user_cfg.assignment(key).unwrap();
+            for include in assignment.include() {
+                FileOption::parse_all(with_defaults(include.options(), defaults)).map_err(
+                    |source| ParseError::Option {
+                        key: key.to_owned(),
+                        source,
+                    },
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The token and domain are only resolved once Canvas is first reached, so that the commands
+    /// which do not reach it work without them.
+    pub fn new(mut user_cfg: config::Config, root: path::PathBuf) -> Result<Self, ParseError> {
+        user_cfg.resolve_urls()?;
+        let timezone = user_cfg
+            .timezone()
+            .map(|timezone| {
+                timezone
+                    .parse()
+                    .map_err(|_| ParseError::Timezone(timezone.to_owned()))
+            })
+            .transpose()?;
+        Ok(Self {
             user_cfg,
+            root,
+            credentials: OnceCell::new(),
+            timezone,
+            course_override: None,
+            extra_includes: Vec::new(),
             courses: OnceCell::new(),
             assignments: OnceCell::new(),
+        })
+    }
+
+    /// The time `time` in the timezone of the config, or in the local timezone of the system if
+    /// the config gives none. Only for display: the dates from Canvas are kept as they are.
+    pub fn display_time(&self, time: &DateTime<Local>) -> DateTime<FixedOffset> {
+        match self.timezone {
+            Some(timezone) => time.with_timezone(&timezone).fixed_offset(),
+            None => time.fixed_offset(),
         }
     }
 
-    pub fn get_token(&self) -> &str {
-        self.user_cfg.token()
+    /// Use `course` instead of the course in the config. This must be done before any course or
+    /// assignment has been fetched.
+    pub fn override_course(&mut self, course: config::Identifier) {
+        self.course_override = Some(course);
+    }
+
+    /// Stage `include` along with the includes in the config, for any assignment. Fails if an
+    /// option of the include is invalid.
+    pub fn add_include(&mut self, include: config::Path) -> Result<(), FileOptionError> {
+        FileOption::parse_all(include.options())?;
+        self.extra_includes.push(include);
+        Ok(())
+    }
+
+    /// The config as read, with the course and assignment ids filled in from any urls.
+    pub fn config(&self) -> &config::Config {
+        &self.user_cfg
     }
 
-    pub fn get_domain(&self) -> &str {
-        self.user_cfg.domain()
+    pub fn root(&self) -> &path::Path {
+        &self.root
+    }
+
+    fn get_credentials(&self) -> Result<&(String, String), BuildError> {
+        self.credentials
+            .get_or_try_init(|| -> Result<_, BuildError> {
+                Ok((
+                    self.user_cfg.resolve_token()?,
+                    self.user_cfg
+                        .resolve_domain(env::var(config::DOMAIN_VAR).ok().as_deref())?,
+                ))
+            })
+    }
+
+    pub fn get_token(&self) -> Result<&str, BuildError> {
+        Ok(&self.get_credentials()?.0)
+    }
+
+    pub fn get_domain(&self) -> Result<&str, BuildError> {
+        Ok(&self.get_credentials()?.1)
+    }
+
+    /// The course with the given `name`, `id` or both among the courses of the user, regardless
+    /// of the course in the config.
+    pub fn get_course(
+        &self,
+        name: Option<&str>,
+        id: Option<u64>,
+    ) -> Result<&canvas::Course, BuildError> {
+        let courses = self.get_courses()?;
+        let ident = config::Identifier::new(name.map(str::to_owned), id);
+        let id =
+            Identifier::try_match_among(courses.iter().map(Identifier::from).collect(), &ident)?
+                .id();
+        // the id was matched among these very courses, so it is present
+        Ok(courses.iter().find(|course| course.id() == id).unwrap())
     }
 
     pub fn get_course_id(&self) -> Result<u64, BuildError> {
-        let courses = self.get_courses()?.iter().map(Identifier::from).collect();
-        let course_ident = Identifier::try_match_among(courses, self.user_cfg.course_ident())?;
-        Ok(course_ident.id())
+        Ok(self.get_selected_course()?.id())
     }
 
     fn get_assignment_ident(&self, key: &str) -> Result<Identifier<'_>, BuildError> {
-        let assignments = self
-            .get_assignments()?
-            .into_iter()
-            .map(Identifier::from)
-            .collect();
+        let assignments = self.get_assignments()?;
+        // the course may be new, or its assignments hidden from students
+        if assignments.is_empty() {
+            return Err(BuildError::NoAssignments);
+        }
+        let assignments = assignments.iter().map(Identifier::from).collect();
         let assignment_ident =
-            Identifier::try_match_among(assignments, self.user_cfg.assignment(key)?.ident())?;
+            Identifier::try_match_among(assignments, self.user_cfg.assignment(key)?.ident())
+                .map_err(|source| BuildError::AssignmentIdent {
+                    key: key.to_owned(),
+                    source,
+                })?;
 
         Ok(assignment_ident)
     }
 
+    /// Fail if Canvas would reject a submission to the assignment `key` at the time `now`,
+    /// because the assignment is not yet open or is already locked.
+    pub fn check_open(&self, key: &str, now: &DateTime<Local>) -> Result<(), BuildError> {
+        let assignment = self.get_assignment(key)?;
+        if !assignment.is_open(now) {
+            Err(BuildError::NotYetOpen(key.to_owned()))
+        } else if assignment.is_locked(now) {
+            Err(BuildError::Locked(key.to_owned()))
+        } else {
+            Ok(())
+        }
+    }
+
     pub fn get_assignment_id(&self, key: &str) -> Result<u64, BuildError> {
         Ok(self.get_assignment_ident(key)?.id())
     }
@@ -462,57 +891,776 @@ impl Wall {
         Ok(self.get_assignment_ident(key)?.name())
     }
 
+    pub fn get_assignment_note(&self, key: &str) -> Result<Option<&str>, BuildError> {
+        Ok(self.user_cfg.assignment(key)?.note())
+    }
+
+    /// The file name of the archive which the includes of the assignment `key` are bundled into,
+    /// if they are bundled.
+    pub fn get_bundle(&self, key: &str) -> Result<Option<&str>, BuildError> {
+        Ok(self.user_cfg.assignment(key)?.bundle())
+    }
+
+    pub fn get_pre_submit_hook(&self, key: &str) -> Result<Option<&str>, BuildError> {
+        Ok(self.user_cfg.assignment(key)?.pre_submit())
+    }
+
+    pub fn get_post_submit_hook(&self, key: &str) -> Result<Option<&str>, BuildError> {
+        Ok(self.user_cfg.assignment(key)?.post_submit())
+    }
+
+    /// How lists are fetched from Canvas: `per_page` items at a time (by default, and at most,
+    /// 100) and no more than `max_items` in total.
+    pub fn get_paging(&self) -> canvas::Paging {
+        canvas::Paging::new(
+            self.user_cfg
+                .per_page()
+                .unwrap_or(canvas::Paging::MAX_PER_PAGE),
+            self.user_cfg.max_items(),
+        )
+    }
+
+    /// The limit on the total size of the files of a submission: `max_upload_bytes` from the
+    /// config, or `DEFAULT_MAX_UPLOAD_BYTES`.
+    pub fn get_max_upload_bytes(&self) -> u64 {
+        self.user_cfg
+            .max_upload_bytes()
+            .unwrap_or(DEFAULT_MAX_UPLOAD_BYTES)
+    }
+
+    /// How long the upload of a single file may take: `upload_timeout_secs` from the config, or
+    /// `canvas::UploadClient::DEFAULT_TIMEOUT`.
+    pub fn get_upload_timeout(&self) -> time::Duration {
+        self.user_cfg
+            .upload_timeout_secs()
+            .map(time::Duration::from_secs)
+            .unwrap_or(canvas::UploadClient::DEFAULT_TIMEOUT)
+    }
+
+    /// The canvas assignment matched by the config entry `key`.
+    pub fn get_assignment(&self, key: &str) -> Result<&canvas::Assignment, BuildError> {
+        let id = self.get_assignment_id(key)?;
+        // the id was matched among these very assignments, so it is present
+        Ok(self
+            .get_assignments()?
+            .iter()
+            .find(|assignment| assignment.id() == id)
+            .unwrap())
+    }
+
+    /// The key of the assignment to use when none is given, see
+    /// `config::Config::default_assignment`.
+    pub fn default_assignment_key(&self) -> Option<&str> {
+        self.user_cfg.default_assignment()
+    }
+
+    /// The keys of the assignments in the config, in sorted order.
+    pub fn assignment_keys(&self) -> impl Iterator<Item = &str> + '_ {
+        self.user_cfg.assignment_keys()
+    }
+
+    /// The assignment keys in the config matching the glob `pattern`, e.g. `lab*`, in sorted
+    /// order. Fails if no key matches.
+    pub fn get_matching_keys(&self, pattern: &str) -> Result<Vec<&str>, BuildError> {
+        let glob = globset::Glob::new(pattern)?.compile_matcher();
+        let keys: Vec<_> = self
+            .assignment_keys()
+            .filter(|key| glob.is_match(key))
+            .collect();
+        if keys.is_empty() {
+            return Err(BuildError::NoMatch(pattern.to_owned()));
+        }
+        Ok(keys)
+    }
+
+    /// The latest submission to the assignment `key`, if anything has been submitted.
+    pub fn get_latest_submission(
+        &self,
+        key: &str,
+    ) -> Result<Option<canvas::Submission>, BuildError> {
+        let submission = canvas::get_own_submission(
+            self.get_token()?,
+            self.get_domain()?,
+            self.get_course_id()?,
+            self.get_assignment_id(key)?,
+            &[],
+        )?;
+        Ok(Some(submission).filter(canvas::Submission::is_submitted))
+    }
+
+    /// The submitted attempt number `attempt`, counting from 1, at the assignment `key`.
+    pub fn get_submission_attempt(
+        &self,
+        key: &str,
+        attempt: u64,
+    ) -> Result<canvas::Submission, BuildError> {
+        let history = self.get_submission_history(key)?;
+        let attempts = history.len();
+        history
+            .into_iter()
+            .zip(1..)
+            .find(|(submission, i)| submission.attempt().unwrap_or(*i) == attempt)
+            .map(|(submission, _)| submission)
+            .ok_or_else(|| BuildError::NoSuchAttempt {
+                key: key.to_owned(),
+                attempt,
+                attempts,
+            })
+    }
+
+    /// The assignment `key` together with the latest submission to it.
+    pub fn get_assignment_status(&self, key: &str) -> Result<AssignmentStatus, BuildError> {
+        let assignment = self.get_assignment(key)?.clone();
+        let submission = self.get_latest_submission(key)?;
+        Ok(AssignmentStatus::new(assignment, submission))
+    }
+
+    /// Every submitted attempt at the assignment `key`, oldest first. Empty if nothing has been
+    /// submitted.
+    pub fn get_submission_history(&self, key: &str) -> Result<Vec<canvas::Submission>, BuildError> {
+        let submission = canvas::get_own_submission(
+            self.get_token()?,
+            self.get_domain()?,
+            self.get_course_id()?,
+            self.get_assignment_id(key)?,
+            &[canvas::GetSubmissionInclude::SubmissionHistory],
+        )?;
+        let mut history = if submission.history().is_empty() {
+            vec![submission]
+        } else {
+            submission.history().to_vec()
+        };
+        history.retain(canvas::Submission::is_submitted);
+        history.sort_by_key(|s| s.submitted_at().copied());
+        Ok(history)
+    }
+
+    /// The comments on the submission to the assignment `key`, oldest first.
+    pub fn get_submission_comments(
+        &self,
+        key: &str,
+    ) -> Result<Vec<canvas::SubmissionComment>, BuildError> {
+        let submission = canvas::get_own_submission(
+            self.get_token()?,
+            self.get_domain()?,
+            self.get_course_id()?,
+            self.get_assignment_id(key)?,
+            &[canvas::GetSubmissionInclude::SubmissionComments],
+        )?;
+        let mut comments = submission.comments().to_vec();
+        comments.sort_by_key(|comment| *comment.created_at());
+        Ok(comments)
+    }
+
     pub fn get_assignment_file_paths<'a>(
         &'a self,
         key: &'a str,
-    ) -> Result<
-        impl Iterator<
-                Item = (
-                    Result<IncludePath, IncludeError>,
-                    Vec<Result<FileOption, FileOptionError>>,
-                ),
-            > + 'a,
-        BuildError,
-    > {
+    ) -> Result<impl Iterator<Item = IncludeEntry> + 'a, BuildError> {
         let include_paths = self.user_cfg.assignment(key)?.include();
+        let defaults = self.user_cfg.defaults().options();
 
         Ok(include_paths
             .into_iter()
-            .map(|include| (include.path(), include.options()))
+            .chain(&self.extra_includes)
+            .map(move |include| (include.path(), with_defaults(include.options(), defaults)))
             .map(move |(p, opts)| {
                 (
-                    IncludePath::try_find(p),
-                    opts.into_iter().map(FileOption::try_from).collect(),
+                    IncludePath::try_find(&self.root, p, opts.contains("outside_root")),
+                    FileOption::parse_all(opts),
                 )
             }))
     }
 
+    /// The include entries of the assignment `key`. A missing path, or an option which is
+    /// invalid or has no use for its include, is an error naming the assignment.
+    pub fn get_staged_includes(&self, key: &str) -> Result<Vec<StagedInclude>, BuildError> {
+        let bundled = self.get_bundle(key)?.is_some();
+        self.get_assignment_file_paths(key)?
+            .map(|(path, options)| {
+                let path = path.map_err(|source| BuildError::Include {
+                    key: key.to_owned(),
+                    source,
+                })?;
+                let options = options
+                    .and_then(|options| check_zip_options(&path, options, bundled))
+                    .map_err(|source| BuildError::Option {
+                        key: key.to_owned(),
+                        source,
+                    })?;
+                Ok(StagedInclude { path, options })
+            })
+            .collect()
+    }
+
     fn get_courses(&self) -> Result<&Vec<canvas::Course>, BuildError> {
-        let courses = self
-            .courses
-            .get_or_try_init(|| canvas::get_courses(self.get_token(), self.get_domain()))?;
+        let courses = self.courses.get_or_try_init(|| -> Result<_, BuildError> {
+            Ok(canvas::get_courses(
+                self.get_token()?,
+                self.get_domain()?,
+                self.get_paging(),
+            )?)
+        })?;
 
         Ok(courses)
     }
 
     fn get_selected_course(&self) -> Result<Identifier<'_>, BuildError> {
         let courses = self.get_courses()?.iter().map(Identifier::from).collect();
-        let selected_course = self.user_cfg.course_ident();
-        Ok(Identifier::try_match_among(courses, &selected_course)?)
+        let selected_course = match &self.course_override {
+            Some(course) => course,
+            None => self.user_cfg.course_ident(),
+        };
+        Ok(Identifier::try_match_among(courses, selected_course)?)
+    }
+
+    /// The keys of the assignments in the config, ordered by due date with undated assignments
+    /// last, and by key among equally dated ones.
+    pub fn get_keys_by_due_date(&self) -> Result<Vec<&str>, BuildError> {
+        let mut keyed = Vec::new();
+        for key in self.assignment_keys() {
+            keyed.push((key, self.get_assignment(key)?));
+        }
+        // a stable sort keeps the keys in sorted order among equal dates
+        keyed.sort_by(|(_, a), (_, b)| a.cmp_due(b));
+        Ok(keyed.into_iter().map(|(key, _)| key).collect())
+    }
+
+    /// The assignment groups of the course in their order on Canvas, each with the keys of the
+    /// assignments in the config which belong to it, by due date. Groups without any such
+    /// assignment are left out.
+    pub fn get_assignment_groups(
+        &self,
+    ) -> Result<Vec<(canvas::AssignmentGroup, Vec<&str>)>, BuildError> {
+        let mut groups = canvas::get_assignment_groups(
+            self.get_token()?,
+            self.get_domain()?,
+            self.get_course_id()?,
+            self.get_paging(),
+        )?;
+        groups.sort_by_key(canvas::AssignmentGroup::position);
+
+        let keys_by_due_date = self.get_keys_by_due_date()?;
+        let mut keyed_groups = Vec::new();
+        for group in groups {
+            let mut keys = Vec::new();
+            for &key in &keys_by_due_date {
+                if self.get_assignment(key)?.assignment_group_id() == Some(group.id()) {
+                    keys.push(key);
+                }
+            }
+            if !keys.is_empty() {
+                keyed_groups.push((group, keys));
+            }
+        }
+        Ok(keyed_groups)
     }
 
     fn get_assignments(&self) -> Result<&Vec<canvas::Assignment>, BuildError> {
-        Ok(self
-            .assignments
+        self.assignments
             .get_or_try_init(|| -> Result<_, BuildError> {
                 Ok(canvas::get_assignments(
-                    self.get_token(),
-                    self.get_domain(),
+                    self.get_token()?,
+                    self.get_domain()?,
                     self.get_course_id()?,
+                    self.get_paging(),
                 )?)
-            })?)
+            })
     }
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+
+    /// A wall rooted at this crate, with a token, a domain and the course "Course", and the
+    /// config lines `extra`, which may begin with top level keys.
+    pub(crate) fn test_wall(extra: &str) -> Wall {
+        let source = format!(
+            "token = \"1234\"\ndomain = \"example.instructure.com\"\n{}\n[course]\nname = \"Course\"\n",
+            extra
+        );
+        let root = path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        Wall::new(toml::from_str(&source).unwrap(), root).unwrap()
+    }
+
+    #[test]
+    fn parse_compression_options() {
+        let parse = |s| FileOption::try_from(s).ok();
+
+        assert_eq!(
+            parse("compression=stored"),
+            Some(FileOption::Compression(Compression::Stored))
+        );
+        assert_eq!(
+            parse("compression=deflate"),
+            Some(FileOption::Compression(Compression::Deflate(None)))
+        );
+        assert_eq!(
+            parse("compression=deflate:9"),
+            Some(FileOption::Compression(Compression::Deflate(Some(9))))
+        );
+        assert_eq!(parse("compression=deflate:10"), None);
+        assert_eq!(parse("compression=bzip"), None);
+        assert_eq!(
+            FileOption::Compression(Compression::Deflate(Some(9))).to_string(),
+            "compression=deflate:9"
+        );
+    }
+
+    #[test]
+    fn parse_option_lists() {
+        assert_eq!(
+            FileOption::parse_all(vec!["zip", "compression=stored", "zip"]).unwrap(),
+            vec![
+                FileOption::Zip,
+                FileOption::Compression(Compression::Stored)
+            ]
+            .into_iter()
+            .collect()
+        );
+        assert!(matches!(
+            FileOption::parse_all(vec!["compression=stored", "compression=deflate"]),
+            Err(FileOptionError::Conflict(_, _))
+        ));
+        assert!(matches!(
+            FileOption::parse_all(vec!["zip", "unzip"]),
+            Err(FileOptionError::Unexpected(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_option_is_caught() {
+        let wall = test_wall(
+            r#"
+[assignment.lab]
+name = "Lab"
+include = [ { path = "src", options = ["ziip"] } ]
+"#,
+        );
+
+        match wall.validate_options() {
+            Err(ParseError::Option { key, source }) => {
+                assert_eq!(key, "lab");
+                assert_eq!(source.to_string(), "unknown option 'ziip'");
+            }
+            other => panic!("expected an option error, got {:?}", other),
+        }
+
+        // staging must not silently drop the bad option either
+        match wall.get_staged_includes("lab") {
+            Err(BuildError::Option { key, .. }) => assert_eq!(key, "lab"),
+            other => panic!("expected an option error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn compression_without_zip_is_refused() {
+        let wall = test_wall(
+            r#"
+[assignment.lab]
+include = [ { path = "Cargo.toml", options = ["compression=stored"] } ]
+
+[assignment.bundled]
+include = [ { path = "Cargo.toml", options = ["compression=stored"] } ]
+bundle = "lab.zip"
+"#,
+        );
+
+        match wall.get_staged_includes("lab") {
+            Err(BuildError::Option { key, source }) => {
+                assert_eq!(key, "lab");
+                assert_eq!(
+                    source.to_string(),
+                    "the option 'compression=stored' only applies together with 'zip'"
+                );
+            }
+            other => panic!("expected an option error, got {:?}", other),
+        }
+        // a bundle is an archive, which the compression applies to
+        assert!(wall.get_staged_includes("bundled").is_ok());
+    }
+
+    #[test]
+    fn content_type_of_zipped_dir_is_refused() {
+        let wall = test_wall(
+            r#"
+[assignment.lab]
+include = [ { path = "src", options = ["zip", "content_type=text/plain"] } ]
+
+[assignment.file]
+include = [ { path = "Cargo.toml", options = ["zip", "content_type=application/zip"] } ]
+
+[assignment.unzipped]
+include = [ { path = "src", options = ["content_type=text/plain"] } ]
+"#,
+        );
+
+        match wall.get_staged_includes("lab") {
+            Err(BuildError::Option { key, source }) => {
+                assert_eq!(key, "lab");
+                assert_eq!(
+                    source.to_string(),
+                    "the option 'content_type=text/plain' does not apply to a zipped directory"
+                );
+            }
+            other => panic!("expected an option error, got {:?}", other),
+        }
+        assert!(wall.get_staged_includes("file").is_ok());
+        assert!(wall.get_staged_includes("unzipped").is_ok());
+    }
+
+    #[test]
+    fn course_override_is_selected() {
+        let mut wall = test_wall(
+            r#"
+[assignment.lab]
+name = "Lab"
+include = "a.txt"
+"#,
+        );
+        let courses: toml::Value = toml::from_str(
+            r#"courses = [ { id = 1, name = "Course" }, { id = 2, name = "Other course" } ]"#,
+        )
+        .unwrap();
+        wall.courses
+            .set(courses["courses"].clone().try_into().unwrap())
+            .unwrap();
+
+        assert_eq!(wall.get_course_id().unwrap(), 1);
+        wall.override_course(config::Identifier::from("Other course"));
+        assert_eq!(wall.get_course_id().unwrap(), 2);
+        wall.override_course(config::Identifier::from("1"));
+        assert_eq!(wall.get_course_id().unwrap(), 1);
+    }
+
+    #[test]
+    fn include_paths_resolve_relative_to_root() {
+        let root = std::env::temp_dir().join(format!("kerchief-test-{}", std::process::id()));
+        fs::create_dir_all(root.join("sub").join("dir")).unwrap();
+        fs::write(root.join("sub").join("file.txt"), "contents").unwrap();
+
+        let mut wall = test_wall(
+            r#"
+[assignment.1]
+name = "Assignment 1"
+include = [ "sub/file.txt", "sub/dir", "missing.txt" ]
+"#,
+        );
+        // the working directory of the test is not `root`
+        assert_ne!(std::env::current_dir().unwrap(), root);
+        wall.root = root.clone();
+
+        let paths: Vec<_> = wall
+            .get_assignment_file_paths("1")
+            .unwrap()
+            .map(|(p, _)| p)
+            .collect();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            paths,
+            vec![
+                Ok(IncludePath::File(root.join("sub").join("file.txt"))),
+                Ok(IncludePath::Dir(root.join("sub").join("dir"))),
+                Err(IncludeError::NotPresent(root.join("missing.txt"))),
+            ]
+        );
+    }
+
+    #[test]
+    fn added_includes_resolve_relative_to_working_directory() {
+        let root = std::env::temp_dir().join(format!("kerchief-test-cwd-{}", std::process::id()));
+        let cwd = root.join("sub");
+        fs::create_dir_all(cwd.join("dir")).unwrap();
+        fs::write(cwd.join("file.txt"), "contents").unwrap();
+
+        let mut wall = test_wall(
+            r#"
+[assignment.1]
+name = "Assignment 1"
+include = []
+"#,
+        );
+        wall.root = root.clone();
+        // as given by `--include file.txt` and `--include dir:zip` from within `sub`
+        wall.add_include(config::Path::from("file.txt").relative_to(&cwd))
+            .unwrap();
+        wall.add_include(config::Path::from("dir:zip").relative_to(&cwd))
+            .unwrap();
+
+        let includes = wall.get_staged_includes("1");
+        fs::remove_dir_all(&root).unwrap();
+
+        let includes = includes.unwrap();
+        assert_eq!(includes[0].path, IncludePath::File(cwd.join("file.txt")));
+        assert_eq!(includes[1].path, IncludePath::Dir(cwd.join("dir")));
+        assert!(includes[1].options.contains(&FileOption::Zip));
+    }
+
+    #[test]
+    fn key_patterns() {
+        let wall = test_wall(
+            r#"
+[assignment.lab2]
+include = "b.txt"
+
+[assignment.lab1]
+include = "a.txt"
+
+[assignment.report]
+include = "c.txt"
+"#,
+        );
+
+        assert_eq!(
+            wall.get_matching_keys("lab*").unwrap(),
+            vec!["lab1", "lab2"]
+        );
+        assert_eq!(wall.get_matching_keys("report").unwrap(), vec!["report"]);
+        assert!(matches!(
+            wall.get_matching_keys("exam*"),
+            Err(BuildError::NoMatch(_))
+        ));
+        assert!(matches!(
+            wall.get_matching_keys("lab[1"),
+            Err(BuildError::Pattern(_))
+        ));
+    }
+
+    #[test]
+    fn assignment_status() {
+        let assignment: canvas::Assignment = serde_json::from_str(
+            r#"{
+                "id": 1,
+                "name": "Assignment 1",
+                "due_at": "2021-02-15T23:59:00Z",
+                "lock_at": "2021-03-01T23:59:00Z"
+            }"#,
+        )
+        .unwrap();
+        let submission: canvas::Submission = serde_json::from_str(
+            r#"{
+                "id": 7,
+                "submitted_at": "2021-02-14T12:00:00Z",
+                "score": null,
+                "grade": null,
+                "workflow_state": "submitted"
+            }"#,
+        )
+        .unwrap();
+        let unsubmitted: canvas::Submission = serde_json::from_str(
+            r#"{
+                "id": 7,
+                "submitted_at": null,
+                "score": null,
+                "grade": null,
+                "workflow_state": "unsubmitted"
+            }"#,
+        )
+        .unwrap();
+
+        let before = "2021-02-10T12:00:00Z".parse().unwrap();
+        let overdue = "2021-02-20T12:00:00Z".parse().unwrap();
+        let locked = "2021-03-10T12:00:00Z".parse().unwrap();
+
+        let missing = AssignmentStatus::new(assignment.clone(), Some(unsubmitted));
+        assert!(!missing.is_submitted());
+        assert!(!missing.is_overdue(&before));
+        assert!(missing.is_overdue(&overdue) && !missing.is_locked(&overdue));
+        assert!(missing.is_locked(&locked));
+
+        let submitted = AssignmentStatus::new(assignment.clone(), Some(submission));
+        assert!(submitted.is_submitted());
+        assert!(!submitted.is_overdue(&overdue));
+
+        let submitted_since = "2021-02-13T00:00:00Z".parse().unwrap();
+        let due_since = "2021-02-15T00:00:00Z".parse().unwrap();
+        assert!(submitted.is_since(&submitted_since) && submitted.is_since(&due_since));
+        assert!(!submitted.is_since(&overdue));
+        assert!(missing.is_since(&due_since) && !missing.is_since(&overdue));
+        let undated = AssignmentStatus::new(
+            serde_json::from_str(r#"{ "id": 2, "name": "Assignment 2" }"#).unwrap(),
+            None,
+        );
+        assert!(!undated.is_since(&before));
+    }
+
+    #[test]
+    fn include_paths_outside_root_are_refused() {
+        let dir = std::env::temp_dir().join(format!("kerchief-outside-{}", std::process::id()));
+        let root = dir.join("root");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(dir.join("outside.txt"), "contents").unwrap();
+
+        let mut wall = test_wall(
+            r#"
+[assignment.1]
+include = "../outside.txt"
+
+[assignment.2]
+include = { path = "../outside.txt", options = ["outside_root"] }
+"#,
+        );
+        wall.root = root.clone();
+        let refused = wall.get_staged_includes("1");
+        let allowed = wall.get_staged_includes("2");
+        fs::remove_dir_all(&dir).unwrap();
+
+        match refused {
+            Err(BuildError::Include {
+                source: IncludeError::OutsideRoot(_),
+                ..
+            }) => {}
+            other => panic!("expected an outside root error, got {:?}", other),
+        }
+        assert_eq!(allowed.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn names_match_up_to_whitespace() {
+        let courses = vec![
+            Identifier {
+                id: 1,
+                name: "Datorgrafik ",
+            },
+            Identifier {
+                id: 2,
+                name: "Linjär\u{a0}algebra",
+            },
+        ];
+
+        let datorgrafik = config::Identifier::from("Datorgrafik");
+        let matched = Identifier::try_match_among(courses.clone(), &datorgrafik).unwrap();
+        assert_eq!(matched.id(), 1);
+        let algebra = config::Identifier::from("  Linjär   algebra");
+        let matched = Identifier::try_match_among(courses.clone(), &algebra).unwrap();
+        assert_eq!(matched.id(), 2);
+
+        assert!(!names_match("Datorgrafik", "Dator grafik"));
+        let id = config::Identifier::from("3");
+        assert!(Identifier::try_match_among(courses, &id).is_err());
+    }
+
+    #[test]
+    fn timezone_for_display() {
+        let wall = |timezone| {
+            test_wall(&format!(
+                r#"
+timezone = "{}"
+
+[assignment.lab]
+include = "a.txt"
+"#,
+                timezone
+            ))
+        };
+        let time: DateTime<Local> = "2021-02-15T23:59:00Z".parse().unwrap();
+
+        let stockholm = wall("Europe/Stockholm").display_time(&time);
+        assert_eq!(stockholm.to_rfc3339(), "2021-02-16T00:59:00+01:00");
+        let utc = wall("UTC").display_time(&time);
+        assert_eq!(utc.to_rfc3339(), "2021-02-15T23:59:00+00:00");
+        let user_cfg =
+            toml::from_str("timezone = \"Mars/Olympus_Mons\"\n[course]\n[assignment]\n").unwrap();
+        assert!(matches!(
+            Wall::new(user_cfg, std::env::temp_dir()),
+            Err(ParseError::Timezone(_))
+        ));
+    }
+
+    #[test]
+    fn course_without_assignments() {
+        let wall = test_wall(
+            r#"
+[assignment.lab]
+name = "Lab"
+include = "a.txt"
+"#,
+        );
+        wall.assignments.set(Vec::new()).unwrap();
+
+        assert!(matches!(
+            wall.get_assignment_id("lab"),
+            Err(BuildError::NoAssignments)
+        ));
+    }
+
+    #[test]
+    fn default_options_are_merged() {
+        let defaults = [String::from("zip"), String::from("compression=stored")];
+        let merged = |options: &[&'static str]| {
+            let mut merged: Vec<_> = with_defaults(options.iter().copied().collect(), &defaults)
+                .into_iter()
+                .collect();
+            merged.sort_unstable();
+            merged
+        };
+
+        assert_eq!(merged(&[]), ["compression=stored", "zip"]);
+        assert_eq!(
+            merged(&["respect_gitignore"]),
+            ["compression=stored", "respect_gitignore", "zip"]
+        );
+        assert_eq!(
+            merged(&["compression=deflate:9"]),
+            ["compression=deflate:9", "zip"]
+        );
+    }
+
+    #[test]
+    fn config_formats_are_equivalent() {
+        let toml_source = r#"
+token = "1234"
+domain = "example.instructure.com"
+
+[course]
+name = "Course"
+
+[assignment.lab]
+name = "Lab 1"
+include = ["report.pdf", { path = "code", options = ["zip"] }]
+"#;
+        let json_source = r#"{
+    "token": "1234",
+    "domain": "example.instructure.com",
+    "course": { "name": "Course" },
+    "assignment": {
+        "lab": {
+            "name": "Lab 1",
+            "include": ["report.pdf", { "path": "code", "options": ["zip"] }]
+        }
+    }
+}"#;
+        let yaml_source = r#"
+token: "1234"
+domain: example.instructure.com
+course:
+  name: Course
+assignment:
+  lab:
+    name: Lab 1
+    include:
+      - report.pdf
+      - path: code
+        options: [zip]
+"#;
+        let parse = |name: &str, source: &str| {
+            parse_config(path::Path::new(name), source).expect("ought to be a valid config")
+        };
+
+        let from_toml = parse("kerchief.toml", toml_source);
+        assert_eq!(parse("kerchief.json", json_source), from_toml);
+        assert_eq!(parse("kerchief.yaml", yaml_source), from_toml);
+        assert_eq!(parse("kerchief.yml", yaml_source), from_toml);
+        assert!(matches!(
+            parse_config(path::Path::new("kerchief.json"), toml_source),
+            Err(ParseError::ParseJson(_))
+        ));
+    }
+
+    #[test]
+    fn wall_can_be_shared_between_threads() {
+        fn assert_shareable<T: Send + Sync + Clone>() {}
+        assert_shareable::<Wall>();
+    }
+}
@@ -0,0 +1,128 @@
+//! The record of the files uploaded by a submission that has not been checked out yet, kept in
+//! `$KERCHIEF_ROOT/.kerchief/pending-<digest>.json` so that an interrupted submission can be
+//! resumed without uploading those files again. The file is named by a digest of the assignment
+//! key, as a key may hold characters which are not allowed in a file name.
+
+use crate::stage::StageError;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::{fs, io, path};
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub(crate) struct PendingUploads {
+    assignment_id: u64,
+    /// The uploaded files by payload name.
+    files: BTreeMap<String, PendingFile>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct PendingFile {
+    digest: String,
+    file_id: u64,
+}
+
+impl PendingUploads {
+    pub(crate) fn path(root: &path::Path, key: &str) -> path::PathBuf {
+        let digest = format!("{:x}", Sha256::digest(key.as_bytes()));
+        root.join(".kerchief")
+            .join(format!("pending-{}.json", &digest[..16]))
+    }
+
+    /// The record at `path` of the uploads to `assignment_id`. A missing or unreadable record,
+    /// or one for another assignment, counts as empty.
+    pub(crate) fn load(path: &path::Path, assignment_id: u64) -> Self {
+        let pending = fs::read(path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<PendingUploads>(&bytes).ok());
+        match pending {
+            Some(pending) if pending.assignment_id == assignment_id => pending,
+            _ => Self {
+                assignment_id,
+                files: BTreeMap::new(),
+            },
+        }
+    }
+
+    pub(crate) fn save(&self, path: &path::Path) -> Result<(), StageError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_vec_pretty(self).map_err(io::Error::from)?;
+        fs::write(path, json)?;
+        Ok(())
+    }
+
+    pub(crate) fn remove(path: &path::Path) -> Result<(), StageError> {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// The id of the earlier upload of `payload_name`, if its contents had the same `digest`.
+    pub(crate) fn file_id(&self, payload_name: &str, digest: &str) -> Option<u64> {
+        self.files
+            .get(payload_name)
+            .filter(|file| file.digest == digest)
+            .map(|file| file.file_id)
+    }
+
+    pub(crate) fn insert(&mut self, payload_name: String, digest: String, file_id: u64) {
+        self.files
+            .insert(payload_name, PendingFile { digest, file_id });
+    }
+}
+
+/// The SHA-256 digest in hex of the contents of the file at `path`. It is the same across
+/// builds, unlike that of the hashers in `std`, since it is kept on disk.
+pub(crate) fn digest(path: &path::Path) -> Result<String, StageError> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pending_uploads_round_trip() {
+        let root = std::env::temp_dir().join(format!("kerchief-pending-{}", std::process::id()));
+        let path = PendingUploads::path(&root, "lab");
+        let payload = root.join("report.txt");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(&payload, "report").unwrap();
+        let report_digest = digest(&payload).unwrap();
+        assert_eq!(
+            report_digest,
+            "845e91831319e89c4d656bdb80c278ac09a7230d61e5dfd2e1b1fbb436ac8917"
+        );
+
+        let mut pending = PendingUploads::load(&path, 1);
+        assert_eq!(pending.file_id("report.txt", &report_digest), None);
+        pending.insert(String::from("report.txt"), report_digest.clone(), 42);
+        pending.save(&path).unwrap();
+
+        let pending = PendingUploads::load(&path, 1);
+        assert_eq!(pending.file_id("report.txt", &report_digest), Some(42));
+        fs::write(&payload, "changed report").unwrap();
+        assert_eq!(
+            pending.file_id("report.txt", &digest(&payload).unwrap()),
+            None
+        );
+        // a record of another assignment is not reused
+        let other = PendingUploads::load(&path, 2);
+        assert_eq!(other.file_id("report.txt", &report_digest), None);
+
+        // a key is never taken as a path
+        let odd = PendingUploads::path(&root, "../lab/1");
+        assert_eq!(odd.parent(), path.parent());
+        assert_ne!(odd, path);
+
+        PendingUploads::remove(&path).unwrap();
+        PendingUploads::remove(&path).unwrap();
+        assert!(!path.exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,1278 @@
+//! Staging the includes of an assignment and submitting them.
+
+use crate::pending::{digest, PendingUploads};
+use crate::{BuildError, Compression, FileOption, IncludePath, StagedInclude, Wall};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::WalkBuilder;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::ffi::{OsStr, OsString};
+use std::sync::Mutex;
+use std::{fs, io, path, process, thread, time};
+use thiserror::Error;
+use walkdir::WalkDir;
+
+#[derive(Debug, Error)]
+pub enum StageError {
+    #[error("no files to submit for '{0}': the includes are empty or entirely ignored")]
+    Empty(String),
+    #[error(
+        "the staged files total {total} bytes, over the limit of {max_size} bytes; \
+         the largest are:\n{largest}"
+    )]
+    TooLarge {
+        total: u64,
+        max_size: u64,
+        largest: String,
+    },
+    #[error("the staged file name {0:?} is not valid unicode")]
+    FileName(OsString),
+    #[error("the bundle '{0}' is not a plain file name")]
+    BundleName(String),
+    #[error(
+        "the option '{option}' of the include {} does not apply to a bundled submission",
+        path.display()
+    )]
+    BundleOption { option: String, path: path::PathBuf },
+    #[error("more than one include is bundled under the name '{0}'")]
+    BundleConflict(String),
+    #[error("the new name '{0}' is not a plain file name")]
+    RenameName(String),
+    #[error("{0} files are staged, but only a single file can be given a new name")]
+    RenameMany(usize),
+    #[error("the assignment does not accept file uploads, only: {}", .0.join(", "))]
+    UploadNotAccepted(Vec<String>),
+    #[error("the assignment does not accept a text entry, only: {}", .0.join(", "))]
+    TextEntryNotAccepted(Vec<String>),
+    #[error(
+        "this assignment only accepts: {}; not accepted: {}",
+        .allowed.join(", "),
+        .refused.join(", ")
+    )]
+    ExtensionNotAllowed {
+        allowed: Vec<String>,
+        refused: Vec<String>,
+    },
+    #[error("the output directory {} is not empty", .0.display())]
+    OutputNotEmpty(path::PathBuf),
+    #[error("the hook `{hook}` failed ({status})")]
+    Hook {
+        hook: String,
+        status: process::ExitStatus,
+    },
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Zip(#[from] zip::result::ZipError),
+    #[error(transparent)]
+    Ignore(#[from] ignore::Error),
+    #[error(transparent)]
+    Walk(#[from] walkdir::Error),
+    #[error(transparent)]
+    Prefix(#[from] path::StripPrefixError),
+    #[error(transparent)]
+    Time(#[from] time::SystemTimeError),
+}
+
+/// How `Wall::submit` stages and uploads a submission.
+#[derive(Clone, Debug)]
+pub struct SubmitOptions {
+    /// The number of files uploaded at a time.
+    pub jobs: usize,
+    /// The limit on the total size of the files, instead of the one in the config.
+    pub max_size: Option<u64>,
+    /// Keep the staging directory instead of removing it.
+    pub keep_temp: bool,
+    /// Submit even if the assignment is not yet open or is locked.
+    pub force: bool,
+    /// The name to upload the single staged file under.
+    pub name: Option<String>,
+    /// Submit this text as a text entry instead of uploading the includes.
+    pub text: Option<String>,
+}
+
+impl Default for SubmitOptions {
+    fn default() -> Self {
+        Self {
+            jobs: 4,
+            max_size: None,
+            keep_temp: false,
+            force: false,
+            name: None,
+            text: None,
+        }
+    }
+}
+
+/// The questions which `Wall::submit` asks along the way, so that a submission can be reviewed
+/// or cancelled before it is made. Each answer goes ahead by default.
+pub trait SubmitPrompts {
+    type Error: From<BuildError>;
+
+    /// Whether to submit again to an assignment whose latest submission is `previous`.
+    fn resubmit(&mut self, _previous: &canvas::Submission) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+
+    /// Run the `pre_submit` hook `hook` from the kerchief root `root`, before anything is
+    /// staged.
+    fn pre_submit(&mut self, hook: &str, root: &path::Path) -> Result<(), Self::Error> {
+        run_hook(hook, root, process::Stdio::inherit()).map_err(BuildError::from)?;
+        Ok(())
+    }
+
+    /// Whether to upload the staged `payload`.
+    fn upload(&mut self, _payload: &Payload) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+
+    /// Whether to reuse the `resumable` files which an interrupted submission already uploaded,
+    /// rather than upload them again.
+    fn resume(&mut self, _resumable: usize) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+}
+
+/// The answers of a submission made without asking: go ahead at every step.
+pub struct Unattended;
+
+impl SubmitPrompts for Unattended {
+    type Error = BuildError;
+}
+
+/// Run the shell command `hook` from the kerchief root `root`, its output going to `stdout`.
+/// The hook gets no stdin, which may be the text of a submission. Fails if the command does not
+/// exit successfully.
+pub fn run_hook(hook: &str, root: &path::Path, stdout: process::Stdio) -> Result<(), StageError> {
+    let mut command = if cfg!(windows) {
+        let mut command = process::Command::new("cmd");
+        command.arg("/C");
+        command
+    } else {
+        let mut command = process::Command::new("sh");
+        command.arg("-c");
+        command
+    };
+    let status = command
+        .arg(hook)
+        .current_dir(root)
+        .stdin(process::Stdio::null())
+        .stdout(stdout)
+        .status()?;
+    if !status.success() {
+        return Err(StageError::Hook {
+            hook: hook.to_owned(),
+            status,
+        });
+    }
+    Ok(())
+}
+
+/// A staging directory unique to this invocation, located under `$KERCHIEF_ROOT/.kerchief`.
+/// The directory is removed when the guard is dropped, however `submit` exits, unless `keep`
+/// is set.
+pub struct StagingDir {
+    path: path::PathBuf,
+    keep: bool,
+}
+
+impl StagingDir {
+    pub fn create(root: &path::Path, keep: bool) -> Result<Self, StageError> {
+        let millis = time::SystemTime::now()
+            .duration_since(time::UNIX_EPOCH)?
+            .as_millis();
+        let path = root
+            .join(".kerchief")
+            .join(format!("temp-{}-{}", process::id(), millis));
+        fs::create_dir_all(&path)?;
+        Ok(Self { path, keep })
+    }
+
+    /// A staging directory at `path`, created if missing, which is kept afterwards. Fails if
+    /// there is anything in it already, as it would be mistaken for staged files.
+    pub fn at(path: &path::Path) -> Result<Self, StageError> {
+        fs::create_dir_all(path)?;
+        if fs::read_dir(path)?.next().is_some() {
+            return Err(StageError::OutputNotEmpty(path.to_owned()));
+        }
+        Ok(Self {
+            path: path.to_owned(),
+            keep: true,
+        })
+    }
+
+    pub fn path(&self) -> &path::Path {
+        &self.path
+    }
+}
+
+impl Drop for StagingDir {
+    fn drop(&mut self) {
+        if self.keep {
+            return;
+        }
+        if let Err(e) = fs::remove_dir_all(&self.path) {
+            log::warn!(
+                "failed to remove the staging directory {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+}
+
+/// The files staged for upload.
+pub struct Payload {
+    /// The directory containing the files.
+    pub dir: path::PathBuf,
+    /// The content types which the user specified for some of the files, by file name.
+    pub content_types: HashMap<String, String>,
+}
+
+impl Payload {
+    /// Give the single staged file the name `name`, under which it is then uploaded. Fails if
+    /// `name` is not a plain file name, or if more than one file is staged.
+    pub fn rename_single(&mut self, name: &str) -> Result<(), StageError> {
+        if path::Path::new(name).file_name() != Some(OsStr::new(name)) {
+            return Err(StageError::RenameName(name.to_owned()));
+        }
+        let files = payload_files(self)?;
+        let (path, payload_name) = match files.as_slice() {
+            [file] => file,
+            _ => return Err(StageError::RenameMany(files.len())),
+        };
+        fs::rename(path, self.dir.join(name))?;
+        if let Some(content_type) = self.content_types.remove(payload_name) {
+            self.content_types.insert(name.to_owned(), content_type);
+        }
+        Ok(())
+    }
+}
+
+/// A submission made by `Wall::upload_and_submit`.
+pub struct Receipt {
+    pub submission: canvas::Submission,
+    /// The name and the Canvas file id of each uploaded file, by name.
+    pub files: Vec<(String, u64)>,
+}
+
+impl Wall {
+    /// Submit to the assignment `key`: run its `pre_submit` hook, stage its includes, upload
+    /// them and submit them, or submit the text entry of `opts` instead. `prompts` is asked
+    /// before a resubmission, before the upload and before reusing the uploads of an interrupted
+    /// submission. Unless `opts.force` is set, an assignment which is not yet open or is locked
+    /// is refused before anything else. Returns `None` if a prompt cancelled the submission.
+    pub fn submit<P: SubmitPrompts>(
+        &self,
+        key: &str,
+        opts: SubmitOptions,
+        prompts: &mut P,
+    ) -> Result<Option<Receipt>, P::Error> {
+        if !opts.force {
+            self.check_open(key, &chrono::Local::now())?;
+        }
+        match opts.text {
+            Some(_) => self.check_accepts_text_entry(key)?,
+            None => self.check_accepts_upload(key)?,
+        }
+        let status = self.get_assignment_status(key)?;
+        if let Some(previous) = status.submission() {
+            if !prompts.resubmit(previous)? {
+                return Ok(None);
+            }
+        }
+        if let Some(hook) = self.get_pre_submit_hook(key)? {
+            prompts.pre_submit(hook, self.root())?;
+        }
+
+        if let Some(text) = &opts.text {
+            let submission = canvas::submit_text_entry(
+                self.get_token()?,
+                self.get_domain()?,
+                self.get_course_id()?,
+                self.get_assignment_id(key)?,
+                text,
+            )
+            .map_err(BuildError::from)?;
+            return Ok(Some(Receipt {
+                submission,
+                files: Vec::new(),
+            }));
+        }
+
+        let staging = StagingDir::create(self.root(), opts.keep_temp).map_err(BuildError::from)?;
+        let max_size = opts.max_size.unwrap_or_else(|| self.get_max_upload_bytes());
+        let mut payload = self.stage_includes(key, &staging, Some(max_size))?;
+        if let Some(name) = &opts.name {
+            payload.rename_single(name).map_err(BuildError::from)?;
+        }
+        self.check_extensions(key, &payload)?;
+        if !prompts.upload(&payload)? {
+            return Ok(None);
+        }
+        let resumable = self.get_resumable_uploads(key, &payload)?;
+        if resumable > 0 && !prompts.resume(resumable)? {
+            self.discard_pending_uploads(key)?;
+        }
+        Ok(Some(self.upload_and_submit(key, &payload, opts.jobs)?))
+    }
+
+    /// Fail if the assignment `key` does not accept a text entry.
+    pub fn check_accepts_text_entry(&self, key: &str) -> Result<(), BuildError> {
+        let assignment = self.get_assignment(key)?;
+        if !assignment.accepts(canvas::ONLINE_TEXT_ENTRY) {
+            let types = assignment.submission_types().to_vec();
+            return Err(StageError::TextEntryNotAccepted(types).into());
+        }
+        Ok(())
+    }
+
+    /// Fail if the assignment `key` does not accept file uploads, as Canvas would only refuse
+    /// them once they are uploaded.
+    pub fn check_accepts_upload(&self, key: &str) -> Result<(), BuildError> {
+        let assignment = self.get_assignment(key)?;
+        if !assignment.accepts(canvas::ONLINE_UPLOAD) {
+            let types = assignment.submission_types().to_vec();
+            return Err(StageError::UploadNotAccepted(types).into());
+        }
+        Ok(())
+    }
+
+    /// Fail if the assignment `key` restricts uploads to certain extensions and some file of
+    /// `payload` has another one, which Canvas would only refuse once the files are uploaded.
+    pub fn check_extensions(&self, key: &str, payload: &Payload) -> Result<(), BuildError> {
+        let assignment = self.get_assignment(key)?;
+        let mut refused: Vec<_> = payload_files(payload)?
+            .into_iter()
+            .map(|(_, payload_name)| payload_name)
+            .filter(|payload_name| !assignment.accepts_file_name(payload_name))
+            .collect();
+        if !refused.is_empty() {
+            refused.sort();
+            return Err(StageError::ExtensionNotAllowed {
+                allowed: assignment.allowed_extensions().to_vec(),
+                refused,
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    /// The include entries have their transformations applied (as specified by their
+    /// respective options) and these files are written to the staging directory `temp`. Fails if
+    /// nothing is staged, so that an empty submission is never made, or if the staged files total
+    /// more than `max_size` bytes.
+    pub fn stage_includes(
+        &self,
+        key: &str,
+        temp: &StagingDir,
+        max_size: Option<u64>,
+    ) -> Result<Payload, BuildError> {
+        let temp = temp.path();
+        let ignore_file = self.root().join(".kerchiefignore");
+        let exclusions = Exclusions {
+            ignore_file: Some(ignore_file.as_path()).filter(|p| p.is_file()),
+            patterns: self.user_cfg.defaults().exclude(),
+        };
+        let cache_dir = self.root().join(".kerchief").join("cache");
+        let mut content_types = HashMap::new();
+        let includes = self.get_staged_includes(key)?;
+
+        let staged_count = match self.get_bundle(key)? {
+            Some(bundle) => bundle_includes(includes, bundle, &exclusions, temp)?,
+            None => {
+                let mut staged_count = 0;
+                for include in includes {
+                    let content_type = include.options.iter().find_map(|opt| match opt {
+                        FileOption::ContentType(content_type) => Some(content_type.clone()),
+                        _ => None,
+                    });
+
+                    let staged = apply_include_transforms(
+                        &include.path,
+                        include.options,
+                        &exclusions,
+                        temp,
+                        &cache_dir,
+                    )?;
+                    staged_count += staged.len();
+                    if let Some(content_type) = content_type {
+                        for staged_path in staged {
+                            let name = staged_path.file_name().unwrap().to_string_lossy();
+                            content_types.insert(name.into_owned(), content_type.clone());
+                        }
+                    }
+                }
+                staged_count
+            }
+        };
+        if staged_count == 0 {
+            return Err(StageError::Empty(key.to_owned()).into());
+        }
+        if let Some(max_size) = max_size {
+            check_staged_size(temp, max_size)?;
+        }
+
+        Ok(Payload {
+            dir: temp.to_owned(),
+            content_types,
+        })
+    }
+
+    /// The number of files of `payload` which an earlier, interrupted submission to the
+    /// assignment `key` already uploaded, and which `upload_and_submit` therefore reuses.
+    pub fn get_resumable_uploads(&self, key: &str, payload: &Payload) -> Result<usize, BuildError> {
+        let assignment_id = self.get_assignment_id(key)?;
+        let pending = PendingUploads::load(&PendingUploads::path(self.root(), key), assignment_id);
+        let mut count = 0;
+        for (payload_path, payload_name) in payload_files(payload)? {
+            if pending
+                .file_id(&payload_name, &digest(&payload_path)?)
+                .is_some()
+            {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Forget the files uploaded by an earlier, interrupted submission to the assignment `key`,
+    /// so that they are uploaded again.
+    pub fn discard_pending_uploads(&self, key: &str) -> Result<(), BuildError> {
+        PendingUploads::remove(&PendingUploads::path(self.root(), key))?;
+        Ok(())
+    }
+
+    /// Upload the files of `payload`, at most `jobs` at a time, and submit them to the assignment
+    /// `key`. Each upload is recorded as it completes, and the files which an earlier,
+    /// interrupted submission uploaded are not uploaded again. The record is removed after a
+    /// successful checkout. Returns the submission along with the uploaded files.
+    pub fn upload_and_submit(
+        &self,
+        key: &str,
+        payload: &Payload,
+        jobs: usize,
+    ) -> Result<Receipt, BuildError> {
+        self.check_accepts_upload(key)?;
+        self.check_extensions(key, payload)?;
+        let domain = self.get_domain()?;
+        let token = self.get_token()?;
+        let course_id = self.get_course_id()?;
+        let assignment_id = self.get_assignment_id(key)?;
+        let pending_path = PendingUploads::path(self.root(), key);
+        let pending = PendingUploads::load(&pending_path, assignment_id);
+        let client = canvas::UploadClient::new(token, domain, self.get_upload_timeout())?;
+
+        let mut files = Vec::new();
+        let mut uploads = Vec::new();
+        for (payload_path, payload_name) in payload_files(payload)? {
+            let digest = digest(&payload_path)?;
+            match pending.file_id(&payload_name, &digest) {
+                Some(file_id) => {
+                    log::info!("reusing the earlier upload of {}", payload_name);
+                    files.push((payload_name, file_id));
+                }
+                None => uploads.push((payload_path, payload_name, digest)),
+            }
+        }
+
+        // the uploads are independent, so workers take them from a shared queue
+        let queue = Mutex::new(uploads);
+        let pending = Mutex::new(pending);
+        let upload_all = || -> Result<Vec<(String, u64)>, BuildError> {
+            let mut files = Vec::new();
+            loop {
+                let next = queue.lock().unwrap().pop();
+                let Some((payload_path, payload_name, digest)) = next else {
+                    return Ok(files);
+                };
+                let upload = canvas::submit_assignment_upload(
+                    &client,
+                    course_id,
+                    assignment_id,
+                    &payload_path,
+                    &payload_name,
+                    payload.content_types.get(&payload_name).map(String::as_str),
+                );
+                let recorded = upload.map_err(BuildError::from).and_then(|file_id| {
+                    let mut pending = pending.lock().unwrap();
+                    pending.insert(payload_name.clone(), digest, file_id);
+                    pending.save(&pending_path)?;
+                    Ok(file_id)
+                });
+                match recorded {
+                    Ok(file_id) => files.push((payload_name, file_id)),
+                    Err(e) => {
+                        // no point in uploading the rest
+                        queue.lock().unwrap().clear();
+                        return Err(e);
+                    }
+                }
+            }
+        };
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0..jobs.max(1)).map(|_| scope.spawn(upload_all)).collect();
+            for worker in workers {
+                files.extend(worker.join().expect("an upload thread panicked")?);
+            }
+            Ok::<_, BuildError>(())
+        })?;
+
+        files.sort_unstable();
+        let file_ids = files.iter().map(|(_, file_id)| *file_id).collect();
+        let submission =
+            canvas::submit_assignment_checkout(token, domain, course_id, assignment_id, file_ids)?;
+        PendingUploads::remove(&pending_path)?;
+
+        Ok(Receipt { submission, files })
+    }
+}
+
+/// The files staged in `payload`, with their payload names.
+fn payload_files(payload: &Payload) -> Result<Vec<(path::PathBuf, String)>, StageError> {
+    let mut files = Vec::new();
+    for entry in WalkDir::new(&payload.dir)
+        .min_depth(1)
+        .max_depth(1)
+        .into_iter()
+        .flatten()
+    {
+        let payload_name = entry
+            .file_name()
+            .to_str()
+            .ok_or_else(|| StageError::FileName(entry.file_name().to_owned()))?
+            .to_owned();
+        files.push((entry.path().to_owned(), payload_name));
+    }
+    Ok(files)
+}
+
+/// Fail if the files staged in `temp` total more than `max_size` bytes, listing the largest.
+fn check_staged_size(temp: &path::Path, max_size: u64) -> Result<(), StageError> {
+    let mut sizes = Vec::new();
+    for entry in fs::read_dir(temp)? {
+        let entry = entry?;
+        sizes.push((entry.metadata()?.len(), entry.file_name()));
+    }
+    let total: u64 = sizes.iter().map(|(size, _)| size).sum();
+    if total > max_size {
+        sizes.sort_unstable_by(|a, b| b.cmp(a));
+        let largest: Vec<_> = sizes
+            .iter()
+            .take(5)
+            .map(|(size, name)| format!("    {} ({} bytes)", name.to_string_lossy(), size))
+            .collect();
+        return Err(StageError::TooLarge {
+            total,
+            max_size,
+            largest: largest.join("\n"),
+        });
+    }
+    Ok(())
+}
+
+/// The options for the entries of a zip archive, as given by the compression option among
+/// `opts`. Without one, the zip crate's default is used.
+fn zip_options(opts: &HashSet<FileOption>) -> zip::write::FileOptions {
+    let compression = opts.iter().find_map(|opt| match opt {
+        FileOption::Compression(compression) => Some(compression),
+        _ => None,
+    });
+
+    let options = zip::write::FileOptions::default();
+    match compression {
+        Some(Compression::Stored) => options.compression_method(zip::CompressionMethod::Stored),
+        Some(Compression::Deflate(level)) => options
+            .compression_method(zip::CompressionMethod::Deflated)
+            .compression_level(level.map(i32::from)),
+        None => options,
+    }
+}
+
+/// What to leave out of a directory include: the entries matching the gitignore-style
+/// `ignore_file` or any of the `patterns` from the `[defaults]` of the config.
+struct Exclusions<'a> {
+    ignore_file: Option<&'a path::Path>,
+    patterns: &'a [String],
+}
+
+/// The patterns of `exclusions`, matched relative to the directory `dir_path`. Without any
+/// patterns nothing is ignored.
+fn ignore_matcher(dir_path: &path::Path, exclusions: &Exclusions) -> Result<Gitignore, StageError> {
+    let mut builder = GitignoreBuilder::new(dir_path);
+    if let Some(ignore_file) = exclusions.ignore_file {
+        if let Some(e) = builder.add(ignore_file) {
+            return Err(e.into());
+        }
+    }
+    for pattern in exclusions.patterns {
+        builder.add_line(None, pattern)?;
+    }
+    Ok(builder.build()?)
+}
+
+/// Use the settings `opts` to produce the payload for the given `include` entry. The payload
+/// is created in the directory `temp`. Entries of a directory include matching `exclusions`
+/// are left out. A zipped directory is copied from `cache_dir` if it is unchanged since it was
+/// last zipped. Returns the paths of the created payload files.
+fn apply_include_transforms(
+    include: &IncludePath,
+    opts: HashSet<FileOption>,
+    exclusions: &Exclusions,
+    temp: &path::Path,
+    cache_dir: &path::Path,
+) -> Result<Vec<path::PathBuf>, StageError> {
+    let zip_options = zip_options(&opts);
+    let mut staged = Vec::new();
+
+    match include {
+        IncludePath::File(file_path) => {
+            if opts.contains(&FileOption::Zip) {
+                let file_name = entry_name(path::Path::new(file_path.file_name().unwrap()))?;
+                let target = temp.join(format!("{}.zip", file_name));
+                staged.push(target.clone());
+                let target = fs::File::create(target)?;
+                let mut file = io::BufReader::new(fs::File::open(file_path)?);
+
+                let mut zip = zip::ZipWriter::new(target);
+                zip.start_file(file_name, zip_options)?;
+                io::copy(&mut file, &mut zip)?;
+                zip.finish()?;
+            } else {
+                let target = temp.join(file_path.file_name().unwrap());
+                staged.push(target.clone());
+                // what happens if ´target´ is already taken? possible bug to think about
+                let mut target = fs::File::create(target)?;
+                let mut file = fs::File::open(file_path)?;
+
+                io::copy(&mut file, &mut target)?;
+            }
+        }
+
+        IncludePath::Dir(dir_path) => {
+            let ignore = ignore_matcher(dir_path, exclusions)?;
+            let respect_gitignore = opts.contains(&FileOption::RespectGitignore);
+
+            if opts.contains(&FileOption::Zip) {
+                let target = temp.join(dir_path.with_extension("zip").file_name().unwrap());
+                staged.push(target.clone());
+                let entries = walk_include_dir(dir_path, &ignore, respect_gitignore, true)?;
+
+                // an unchanged directory is not zipped anew
+                let cache_prefix = cache_prefix(dir_path);
+                let cached = cache_dir.join(format!(
+                    "{}{}.zip",
+                    cache_prefix,
+                    fingerprint(&opts, &entries)?
+                ));
+                if cached.is_file() {
+                    fs::copy(&cached, &target)?;
+                    return Ok(staged);
+                }
+
+                let mut zip = zip::ZipWriter::new(fs::File::create(&target)?);
+                for (entry_path, file_type) in entries {
+                    if file_type.is_dir() {
+                        zip.add_directory(
+                            entry_name(entry_path.strip_prefix(dir_path)?)?,
+                            zip_options,
+                        )?;
+                    } else if file_type.is_file() {
+                        zip.start_file(
+                            entry_name(entry_path.strip_prefix(dir_path)?)?,
+                            zip_options,
+                        )?;
+                        let mut file = io::BufReader::new(fs::File::open(&entry_path)?);
+                        io::copy(&mut file, &mut zip)?;
+                    }
+                    // do nothing with symlinks
+                }
+                zip.finish()?;
+                fs::create_dir_all(cache_dir)?;
+                evict_cached(cache_dir, &cache_prefix)?;
+                fs::copy(&target, &cached)?;
+            } else {
+                for (entry_path, file_type) in
+                    walk_include_dir(dir_path, &ignore, respect_gitignore, false)?
+                {
+                    if !file_type.is_file() {
+                        continue;
+                    }
+                    let target = temp.join(entry_path.file_name().unwrap());
+                    staged.push(target.clone());
+                    // what happens if ´target´ is already taken? possible bug to think about
+                    let mut target = fs::File::create(target)?;
+                    let mut file = fs::File::open(&entry_path)?;
+
+                    io::copy(&mut file, &mut target)?;
+                }
+            }
+        }
+    }
+    Ok(staged)
+}
+
+/// Zip all of the `includes` into the single archive `bundle` in `temp`, a file under its file
+/// name and a directory under its directory name. The options `zip` and `content_type` are
+/// refused, since the archive is uploaded as a whole. Returns the number of files bundled.
+fn bundle_includes(
+    includes: Vec<StagedInclude>,
+    bundle: &str,
+    exclusions: &Exclusions,
+    temp: &path::Path,
+) -> Result<usize, StageError> {
+    if path::Path::new(bundle).file_name() != Some(OsStr::new(bundle)) {
+        return Err(StageError::BundleName(bundle.to_owned()));
+    }
+
+    let mut zip = zip::ZipWriter::new(fs::File::create(temp.join(bundle))?);
+    let mut names = HashSet::new();
+    let mut file_count = 0;
+    for include in includes {
+        let (IncludePath::File(include_path) | IncludePath::Dir(include_path)) = &include.path;
+        for option in &include.options {
+            if matches!(option, FileOption::Zip | FileOption::ContentType(_)) {
+                return Err(StageError::BundleOption {
+                    option: option.to_string(),
+                    path: include_path.clone(),
+                });
+            }
+        }
+        let name = entry_name(path::Path::new(include_path.file_name().unwrap()))?;
+        if !names.insert(name.to_owned()) {
+            return Err(StageError::BundleConflict(name.to_owned()));
+        }
+        let zip_options = zip_options(&include.options);
+
+        match &include.path {
+            IncludePath::File(file_path) => {
+                zip.start_file(name, zip_options)?;
+                let mut file = io::BufReader::new(fs::File::open(file_path)?);
+                io::copy(&mut file, &mut zip)?;
+                file_count += 1;
+            }
+            IncludePath::Dir(dir_path) => {
+                let ignore = ignore_matcher(dir_path, exclusions)?;
+                let respect_gitignore = include.options.contains(&FileOption::RespectGitignore);
+                zip.add_directory(name, zip_options)?;
+                for (entry_path, file_type) in
+                    walk_include_dir(dir_path, &ignore, respect_gitignore, true)?
+                {
+                    let entry = format!(
+                        "{}/{}",
+                        name,
+                        entry_name(entry_path.strip_prefix(dir_path)?)?
+                    );
+                    if file_type.is_dir() {
+                        zip.add_directory(entry, zip_options)?;
+                    } else if file_type.is_file() {
+                        zip.start_file(entry, zip_options)?;
+                        let mut file = io::BufReader::new(fs::File::open(&entry_path)?);
+                        io::copy(&mut file, &mut zip)?;
+                        file_count += 1;
+                    }
+                    // do nothing with symlinks
+                }
+            }
+        }
+    }
+    zip.finish()?;
+    Ok(file_count)
+}
+
+/// The name of the zip entry at the relative path `path`. The zip writer marks a name which is
+/// not plain ASCII as UTF-8, so that unzip tools do not read it in another encoding.
+fn entry_name(path: &path::Path) -> Result<&str, StageError> {
+    path.to_str()
+        .ok_or_else(|| StageError::FileName(path.as_os_str().to_owned()))
+}
+
+/// The start of the name of each cached zip of the directory `dir_path`, a digest of the path.
+fn cache_prefix(dir_path: &path::Path) -> String {
+    let digest = format!(
+        "{:x}",
+        Sha256::digest(dir_path.as_os_str().as_encoded_bytes())
+    );
+    format!("{}-", &digest[..16])
+}
+
+/// A digest of a directory as it would be zipped: the options and the path, size and
+/// modification time of each of its `entries`. A change to any of them changes the digest. It
+/// names a file in the cache, so it is SHA-256 rather than a hash which may change between
+/// builds.
+fn fingerprint(
+    opts: &HashSet<FileOption>,
+    entries: &[(path::PathBuf, fs::FileType)],
+) -> Result<String, StageError> {
+    let mut hasher = Sha256::new();
+    let mut opts: Vec<_> = opts.iter().map(ToString::to_string).collect();
+    opts.sort_unstable();
+    for opt in opts {
+        hasher.update(opt.as_bytes());
+        hasher.update([0]);
+    }
+    for (entry_path, file_type) in entries {
+        hasher.update(entry_path.as_os_str().as_encoded_bytes());
+        hasher.update([0]);
+        if file_type.is_file() {
+            let metadata = fs::metadata(entry_path)?;
+            let modified = metadata.modified()?.duration_since(time::UNIX_EPOCH)?;
+            hasher.update(metadata.len().to_le_bytes());
+            hasher.update(modified.as_nanos().to_le_bytes());
+        }
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Remove the zips in `cache_dir` whose names start with `prefix`, those of a directory which
+/// has since changed.
+fn evict_cached(cache_dir: &path::Path, prefix: &str) -> Result<(), StageError> {
+    for entry in fs::read_dir(cache_dir)? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with(prefix) {
+            fs::remove_file(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+/// The entries below `dir_path` which are not matched by `ignore`, each directory listed
+/// before its contents. Only the immediate entries are listed unless `recursive` is set. With
+/// `respect_gitignore`, the entries ignored by the `.gitignore` files of the project are left
+/// out as well.
+fn walk_include_dir(
+    dir_path: &path::Path,
+    ignore: &Gitignore,
+    respect_gitignore: bool,
+    recursive: bool,
+) -> Result<Vec<(path::PathBuf, fs::FileType)>, StageError> {
+    let max_depth = if recursive { None } else { Some(1) };
+    let mut entries = Vec::new();
+
+    if respect_gitignore {
+        let ignore = ignore.clone();
+        for entry in WalkBuilder::new(dir_path)
+            .standard_filters(false)
+            .git_ignore(true)
+            .git_exclude(true)
+            .parents(true)
+            .require_git(false)
+            .max_depth(max_depth)
+            .filter_entry(move |e| {
+                let is_dir = e.file_type().is_some_and(|t| t.is_dir());
+                !ignore.matched(e.path(), is_dir).is_ignore()
+            })
+            .build()
+        {
+            let entry = entry?;
+            if let (1.., Some(file_type)) = (entry.depth(), entry.file_type()) {
+                entries.push((entry.into_path(), file_type));
+            }
+        }
+    } else {
+        let mut walk = WalkDir::new(dir_path).min_depth(1);
+        if let Some(max_depth) = max_depth {
+            walk = walk.max_depth(max_depth);
+        }
+        for entry in walk
+            .into_iter()
+            .filter_entry(|e| !ignore.matched(e.path(), e.file_type().is_dir()).is_ignore())
+        {
+            let entry = entry?;
+            let file_type = entry.file_type();
+            entries.push((entry.into_path(), file_type));
+        }
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::test_wall;
+
+    #[test]
+    fn includes_are_staged() {
+        let root = std::env::temp_dir().join(format!("kerchief-stage-{}", process::id()));
+        fs::create_dir_all(root.join("dir").join("sub")).unwrap();
+        fs::create_dir_all(root.join("empty")).unwrap();
+        fs::write(root.join("report.txt"), "report").unwrap();
+        fs::write(root.join("dir").join("sub").join("code.rs"), "fn main() {}").unwrap();
+
+        let mut wall = test_wall(
+            r#"
+[assignment.lab]
+include = [ "report.txt", { path = "dir", options = ["zip"] } ]
+
+[assignment.empty]
+include = "empty"
+
+[assignment.bundled]
+include = [ "report.txt", "dir" ]
+bundle = "submission.zip"
+
+[assignment.conflict]
+include = [ "report.txt", { path = "dir", options = ["zip"] } ]
+bundle = "submission.zip"
+"#,
+        );
+        wall.root = root.clone();
+
+        let staging = StagingDir::create(&root, false).unwrap();
+        let payload = wall.stage_includes("lab", &staging, None).map(|payload| {
+            let mut names: Vec<_> = fs::read_dir(&payload.dir)
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name())
+                .collect();
+            names.sort();
+            names
+        });
+        let too_large =
+            wall.stage_includes("lab", &StagingDir::create(&root, false).unwrap(), Some(1));
+        let empty = wall.stage_includes("empty", &StagingDir::create(&root, false).unwrap(), None);
+        let bundled_staging = StagingDir::create(&root, false).unwrap();
+        let bundled = wall
+            .stage_includes("bundled", &bundled_staging, None)
+            .map(|payload| {
+                let archive = fs::File::open(payload.dir.join("submission.zip")).unwrap();
+                let archive = zip::ZipArchive::new(archive).unwrap();
+                let mut names: Vec<_> = archive.file_names().map(str::to_owned).collect();
+                names.sort();
+                (fs::read_dir(&payload.dir).unwrap().count(), names)
+            });
+        let conflict =
+            wall.stage_includes("conflict", &StagingDir::create(&root, false).unwrap(), None);
+        drop(bundled_staging);
+        drop(staging);
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(payload.unwrap(), ["dir.zip", "report.txt"]);
+        assert!(matches!(
+            too_large,
+            Err(BuildError::Stage(StageError::TooLarge { .. }))
+        ));
+        assert!(matches!(
+            empty,
+            Err(BuildError::Stage(StageError::Empty(_)))
+        ));
+        assert_eq!(
+            bundled.unwrap(),
+            (
+                1,
+                vec![
+                    String::from("dir/"),
+                    String::from("dir/sub/"),
+                    String::from("dir/sub/code.rs"),
+                    String::from("report.txt")
+                ]
+            )
+        );
+        assert!(matches!(
+            conflict,
+            Err(BuildError::Stage(StageError::BundleOption { .. }))
+        ));
+    }
+
+    #[test]
+    fn zip_entry_names_are_utf8() {
+        let root = std::env::temp_dir().join(format!("kerchief-utf8-{}", process::id()));
+        let dir = root.join("källkod");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("räksmörgås.txt"), "hej").unwrap();
+        let temp = root.join("temp");
+        fs::create_dir_all(&temp).unwrap();
+
+        let staged = apply_include_transforms(
+            &IncludePath::Dir(dir.clone()),
+            std::iter::once(FileOption::Zip).collect(),
+            &Exclusions {
+                ignore_file: None,
+                patterns: &[],
+            },
+            &temp,
+            &root.join("cache"),
+        )
+        .unwrap();
+        let mut archive = zip::ZipArchive::new(fs::File::open(&staged[0]).unwrap()).unwrap();
+        let name = archive.by_index(0).unwrap().name().to_owned();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(name, "räksmörgås.txt");
+    }
+
+    #[test]
+    fn zipped_file_keeps_its_extension() {
+        let root = std::env::temp_dir().join(format!("kerchief-zip-file-{}", process::id()));
+        let temp = root.join("temp");
+        fs::create_dir_all(&temp).unwrap();
+        fs::write(root.join("report.pdf"), "%PDF").unwrap();
+
+        let staged = apply_include_transforms(
+            &IncludePath::File(root.join("report.pdf")),
+            std::iter::once(FileOption::Zip).collect(),
+            &Exclusions {
+                ignore_file: None,
+                patterns: &[],
+            },
+            &temp,
+            &root.join("cache"),
+        )
+        .unwrap();
+        let mut archive = zip::ZipArchive::new(fs::File::open(&staged[0]).unwrap()).unwrap();
+        let name = archive.by_index(0).unwrap().name().to_owned();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(staged, vec![temp.join("report.pdf.zip")]);
+        assert_eq!(name, "report.pdf");
+    }
+
+    #[test]
+    fn changed_directory_replaces_its_cached_zip() {
+        let root = std::env::temp_dir().join(format!("kerchief-cache-{}", process::id()));
+        let dir = root.join("lab");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("main.rs"), "fn main() {}").unwrap();
+        let cache_dir = root.join("cache");
+        let zip_lab = || {
+            let temp = root.join("temp");
+            fs::create_dir_all(&temp).unwrap();
+            apply_include_transforms(
+                &IncludePath::Dir(dir.clone()),
+                std::iter::once(FileOption::Zip).collect(),
+                &Exclusions {
+                    ignore_file: None,
+                    patterns: &[],
+                },
+                &temp,
+                &cache_dir,
+            )
+            .unwrap();
+            fs::remove_dir_all(&temp).unwrap();
+            let mut cached: Vec<_> = fs::read_dir(&cache_dir)
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name())
+                .collect();
+            cached.sort();
+            cached
+        };
+
+        let first = zip_lab();
+        let unchanged = zip_lab();
+        fs::write(dir.join("main.rs"), "fn main() { println!(); }").unwrap();
+        let changed = zip_lab();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(first.len(), 1);
+        assert_eq!(unchanged, first);
+        assert_eq!(changed.len(), 1);
+        assert_ne!(changed, first);
+    }
+
+    #[test]
+    fn default_excludes_apply() {
+        let root = std::env::temp_dir().join(format!("kerchief-exclude-{}", process::id()));
+        fs::create_dir_all(root.join("lab").join("target")).unwrap();
+        fs::write(root.join("lab").join("main.rs"), "fn main() {}").unwrap();
+        fs::write(root.join("lab").join("target").join("main"), "binary").unwrap();
+
+        let mut wall = test_wall(
+            r#"
+[defaults]
+options = ["zip"]
+exclude = ["target/"]
+
+[assignment.lab]
+include = "lab"
+"#,
+        );
+        wall.root = root.clone();
+        let staging = StagingDir::create(&root, false).unwrap();
+        let names = wall.stage_includes("lab", &staging, None).map(|payload| {
+            let archive = fs::File::open(payload.dir.join("lab.zip")).unwrap();
+            let archive = zip::ZipArchive::new(archive).unwrap();
+            archive.file_names().map(str::to_owned).collect::<Vec<_>>()
+        });
+        drop(staging);
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(names.unwrap(), ["main.rs"]);
+    }
+
+    #[test]
+    fn single_file_is_renamed() {
+        let dir = std::env::temp_dir().join(format!("kerchief-rename-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("report.txt"), "report").unwrap();
+        let mut payload = Payload {
+            dir: dir.clone(),
+            content_types: HashMap::new(),
+        };
+        payload
+            .content_types
+            .insert(String::from("report.txt"), String::from("text/plain"));
+
+        let nested = payload.rename_single("sub/report.txt");
+        payload.rename_single("lab1.txt").unwrap();
+        let renamed = fs::read_to_string(dir.join("lab1.txt")).unwrap();
+        fs::write(dir.join("notes.txt"), "notes").unwrap();
+        let many = payload.rename_single("lab1.txt");
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(matches!(nested, Err(StageError::RenameName(_))));
+        assert_eq!(renamed, "report");
+        assert_eq!(
+            payload.content_types.get("lab1.txt").map(String::as_str),
+            Some("text/plain")
+        );
+        assert!(matches!(many, Err(StageError::RenameMany(2))));
+    }
+
+    #[test]
+    fn staging_dir_at_output() {
+        let dir = std::env::temp_dir().join(format!("kerchief-output-{}", process::id()));
+        let output = dir.join("out");
+
+        let staging = StagingDir::at(&output).map(|staging| staging.path().to_owned());
+        fs::write(output.join("report.txt"), "report").unwrap();
+        let not_empty = StagingDir::at(&output).map(drop);
+        let kept = output.is_dir();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(staging.unwrap(), output);
+        assert!(matches!(not_empty, Err(StageError::OutputNotEmpty(_))));
+        assert!(kept);
+    }
+
+    #[test]
+    fn staging_needs_no_token() {
+        let root = std::env::temp_dir().join(format!("kerchief-no-token-{}", process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("report.txt"), "report").unwrap();
+
+        let user_cfg = toml::from_str(
+            r#"
+[course]
+name = "Course"
+
+[assignment.lab]
+include = "report.txt"
+"#,
+        )
+        .unwrap();
+        let wall = Wall::new(user_cfg, root.clone()).unwrap();
+
+        let staging = StagingDir::create(&root, false).unwrap();
+        let staged = wall
+            .stage_includes("lab", &staging, None)
+            .map(|payload| payload.dir.join("report.txt").is_file());
+        drop(staging);
+        fs::remove_dir_all(&root).unwrap();
+
+        assert!(staged.unwrap());
+        assert!(matches!(
+            wall.get_token(),
+            Err(BuildError::Token(config::TokenError::Neither))
+        ));
+    }
+
+    #[test]
+    fn refused_submit_is_an_error() {
+        let wall = test_wall(
+            r#"
+[assignment.locked]
+name = "Locked"
+include = "a.txt"
+
+[assignment.unopened]
+name = "Unopened"
+include = "a.txt"
+"#,
+        );
+        let assignments = serde_json::from_str(
+            r#"[
+                { "id": 1, "name": "Locked", "lock_at": "2021-03-01T23:59:00Z" },
+                { "id": 2, "name": "Unopened", "unlock_at": "2999-01-01T00:00:00Z" }
+            ]"#,
+        )
+        .unwrap();
+        wall.assignments.set(assignments).unwrap();
+
+        assert!(matches!(
+            wall.submit("locked", SubmitOptions::default(), &mut Unattended),
+            Err(BuildError::Locked(key)) if key == "locked"
+        ));
+        assert!(matches!(
+            wall.submit("unopened", SubmitOptions::default(), &mut Unattended),
+            Err(BuildError::NotYetOpen(key)) if key == "unopened"
+        ));
+        let during = "2021-02-15T12:00:00Z".parse().unwrap();
+        assert!(wall.check_open("locked", &during).is_ok());
+    }
+
+    #[test]
+    fn uploads_to_a_text_entry_assignment_are_refused() {
+        let wall = test_wall(
+            r#"
+[assignment.essay]
+name = "Essay"
+include = "essay.txt"
+"#,
+        );
+        let assignments = serde_json::from_str(
+            r#"[ { "id": 1, "name": "Essay", "submission_types": ["online_text_entry"] } ]"#,
+        )
+        .unwrap();
+        wall.assignments.set(assignments).unwrap();
+        let payload = Payload {
+            dir: std::env::temp_dir(),
+            content_types: HashMap::new(),
+        };
+
+        assert!(matches!(
+            wall.submit("essay", SubmitOptions::default(), &mut Unattended),
+            Err(BuildError::Stage(StageError::UploadNotAccepted(_)))
+        ));
+        assert!(matches!(
+            wall.upload_and_submit("essay", &payload, 1),
+            Err(BuildError::Stage(StageError::UploadNotAccepted(types))) if types == ["online_text_entry"]
+        ));
+    }
+
+    #[test]
+    fn disallowed_extensions_are_refused() {
+        let root = std::env::temp_dir().join(format!("kerchief-extensions-{}", process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("report.pdf"), "report").unwrap();
+        fs::write(root.join("notes.docx"), "notes").unwrap();
+        let mut wall = test_wall(
+            r#"
+[assignment.report]
+name = "Report"
+include = [ "report.pdf", "notes.docx" ]
+"#,
+        );
+        wall.root = root.clone();
+        let assignments = serde_json::from_str(
+            r#"[ { "id": 1, "name": "Report", "allowed_extensions": ["pdf", "zip"] } ]"#,
+        )
+        .unwrap();
+        wall.assignments.set(assignments).unwrap();
+
+        // `submit` asks Canvas for the latest submission before staging, so the upload is made
+        // from a payload staged here
+        let staging = StagingDir::create(&root, false).unwrap();
+        let payload = wall.stage_includes("report", &staging, None).unwrap();
+        let submitted = wall.upload_and_submit("report", &payload, 1);
+        drop(staging);
+        fs::remove_dir_all(&root).unwrap();
+
+        match submitted {
+            Err(BuildError::Stage(StageError::ExtensionNotAllowed { allowed, refused })) => {
+                assert_eq!(allowed, ["pdf", "zip"]);
+                assert_eq!(refused, ["notes.docx"]);
+            }
+            _ => panic!("expected the extension to be refused"),
+        }
+    }
+}
@@ -1,165 +1,98 @@
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
 use clap::{App, Arg, SubCommand};
-use std::collections::HashSet;
-use std::io::{BufRead, Write};
-use std::{env, fs, io, path};
+use indicatif::{ProgressBar, ProgressDrawTarget};
+use std::collections::BTreeSet;
+use std::io::{BufRead, IsTerminal, Read, Write};
+use std::{env, fs, io, path, process, time};
+use thiserror::Error;
 use walkdir::WalkDir;
 
-fn find_root() -> anyhow::Result<path::PathBuf> {
-    path::Path::new(".")
-        .canonicalize()?
-        .ancestors()
-        .find(|path| path.join("kerchief.toml").is_file())
-        .map(path::Path::to_owned)
-        .ok_or(anyhow::anyhow!("Found no relevant root"))
-}
+mod table;
 
-fn upload_and_submit(store: model::Wall, key: &str, upload_from_dir: &str) -> anyhow::Result<()> {
-    let domain = store.get_domain();
-    let token = store.get_token();
-    let course_id = store.get_course_id()?;
-    let assignment_id = store.get_assignment_id(key)?;
+static DATE_FORMAT: &str = "%Y-%m-%d %H:%M";
 
-    let mut file_ids = Vec::new();
-    for entry in WalkDir::new(upload_from_dir)
-        .min_depth(1)
-        .max_depth(1)
-        .into_iter()
-        .flatten()
-    {
-        let payload_path = entry.path();
-        let payload_name = entry
-            .file_name()
-            .to_str()
-            .ok_or(anyhow::anyhow!("failed to convert payload file name"))?;
-        let file_id = canvas::submit_assignment_upload(
-            token,
-            domain,
-            course_id,
-            assignment_id,
-            payload_path,
-            payload_name,
-        )?;
-        file_ids.push(file_id);
-    }
-
-    canvas::submit_assignment_checkout(token, domain, course_id, assignment_id, file_ids)?;
+/// `println!` unless the first argument, `quiet`, is true.
+macro_rules! say {
+    ($quiet:expr, $($arg:tt)*) => {
+        if !$quiet {
+            println!($($arg)*);
+        }
+    };
+}
 
-    Ok(())
+#[derive(Debug, Error)]
+enum RootError {
+    #[error(
+        "no kerchief.toml, .json or .yaml found in this or any parent directory; run `kerchief init`"
+    )]
+    NotFound,
+    #[error("failed to resolve the current directory: {0}")]
+    CurrentDir(#[from] io::Error),
 }
 
-/// The include entries have their transformations applied (as specified by their
-/// respective options) and these files are written to a temporary directory (presently
-/// the constant path `$KERCHIEF_ROOT/.kerchief/temp`). Returns the directory path.
-fn stage_includes(store: &model::Wall, key: &str) -> anyhow::Result<String> {
-    let root = find_root()?;
-    env::set_current_dir(&root)?;
-    let temp = path::Path::new(".kerchief").join("temp");
+/// Find the closest ancestor of the current directory containing a config file, such as
+/// `kerchief.toml`. The search does not continue past the home directory, as a config above it
+/// is not meant for us.
+fn find_root() -> Result<path::PathBuf, RootError> {
+    find_config().map(|config| {
+        // the config was found in a directory
+        config.parent().unwrap().to_owned()
+    })
+}
 
-    // We want to ignore the case where the directory wasn't found, but otherwise pass
-    // on the error.
-    let res = fs::remove_dir_all(&temp);
-    if let Err(e) = res {
-        if let io::ErrorKind::NotFound = e.kind() {
-            Ok(())
-        } else {
-            Err(e)
+/// The path of the config file in the closest ancestor of the current directory that has one.
+/// Of several config files in one directory, the first in `model::CONFIG_FILE_NAMES` is taken.
+fn find_config() -> Result<path::PathBuf, RootError> {
+    let home = env::var_os("HOME").map(path::PathBuf::from);
+    for path in path::Path::new(".").canonicalize()?.ancestors() {
+        for name in model::CONFIG_FILE_NAMES.iter() {
+            let config = path.join(name);
+            if config.is_file() {
+                return Ok(config);
+            }
         }
-    } else {
-        res
-    }?;
-
-    fs::create_dir_all(&temp)?;
-
-    for (p, opts) in store.get_assignment_file_paths(key)? {
-        if let Ok(include) = p {
-            apply_include_transforms(&include, opts.into_iter().flatten().collect(), &temp)?;
-        } else if let Err(e) = p {
-            println!("{}", e)
+        if home.as_deref() == Some(path) {
+            break;
         }
     }
-
-    Ok(temp.to_str().unwrap().to_owned())
+    Err(RootError::NotFound)
 }
 
-/// Use the settings `opts` to produce the payload for the given `include` entry. The payload
-/// is created in the directory `temp`.
-fn apply_include_transforms(
-    include: &model::IncludePath,
-    opts: HashSet<model::FileOption>,
-    temp: &path::Path,
+/// Print a warning if the assignment `key` is not yet open or is locked, as Canvas rejects
+/// submissions to an assignment which is not open. `Wall::submit` refuses them unless `force` is
+/// set, e.g. for when the local clock is off.
+fn warn_unavailable(
+    store: &model::Wall,
+    key: &str,
+    force: bool,
+    quiet: bool,
 ) -> anyhow::Result<()> {
-    match include {
-        model::IncludePath::File(file_path) => {
-            if opts.contains(&model::FileOption::Zip) {
-                let file_name = file_path.file_name().unwrap().to_str().unwrap();
-                let target = temp.join(file_name).with_extension(".zip");
-                let target = fs::File::create(target)?;
-                let file = fs::read(file_path)?;
-
-                let mut zip = zip::ZipWriter::new(target);
-                zip.start_file(file_name, Default::default())?;
-                zip.write(&file)?;
-                zip.finish()?;
-            } else {
-                let target = temp.join(file_path.file_name().unwrap());
-                // what happens if ´target´ is already taken? possible bug to think about
-                let mut target = fs::File::create(target)?;
-                let mut file = fs::File::open(file_path)?;
-
-                io::copy(&mut file, &mut target)?;
-            }
-        }
-
-        model::IncludePath::Dir(dir_path) => {
-            if opts.contains(&model::FileOption::Zip) {
-                let target = temp.join(dir_path.with_extension("zip").file_name().unwrap());
-                let target = fs::File::create(target)?;
-                let mut zip = zip::ZipWriter::new(target);
-
-                for entry in WalkDir::new(dir_path)
-                    .min_depth(1)
-                    .contents_first(false)
-                    .into_iter()
-                {
-                    let entry = entry?;
-                    if entry.file_type().is_dir() {
-                        zip.add_directory(
-                            entry.path().strip_prefix(dir_path)?.to_str().unwrap(),
-                            Default::default(),
-                        )?;
-                    } else if entry.file_type().is_file() {
-                        zip.start_file(
-                            entry.path().strip_prefix(dir_path)?.to_str().unwrap(),
-                            Default::default(),
-                        )?;
-                        let file = fs::read(entry.path())?;
-                        // add buffering dumbfuck
-                        zip.write_all(&file)?;
-                    }
-                    // do nothing with symlinks
-                }
-                zip.finish()?;
-            } else {
-                for entry in WalkDir::new(dir_path)
-                    .min_depth(1)
-                    .into_iter()
-                    .filter_entry(|e| e.file_type().is_file())
-                {
-                    let entry = entry?;
-                    let target = temp.join(entry.file_name());
-                    // what happens if ´target´ is already taken? possible bug to think about
-                    let mut target = fs::File::create(target)?;
-                    let mut file = fs::File::open(entry.path())?;
-
-                    io::copy(&mut file, &mut target)?;
-                }
-            }
-        }
+    let refusal = match store.check_open(key, &Local::now()) {
+        Ok(()) => return Ok(()),
+        Err(refusal) => refusal,
+    };
+    let assignment = store.get_assignment(key)?;
+    match (&refusal, assignment.unlock_at(), assignment.lock_at()) {
+        (model::BuildError::NotYetOpen(_), Some(unlock_at), _) => say!(
+            quiet,
+            "Warning: the assignment opens at {}, Canvas will reject the submission.",
+            store.display_time(unlock_at).format(DATE_FORMAT)
+        ),
+        (model::BuildError::Locked(_), _, Some(lock_at)) => say!(
+            quiet,
+            "Warning: the assignment is locked since {}, Canvas will reject the submission.",
+            store.display_time(lock_at).format(DATE_FORMAT)
+        ),
+        (model::BuildError::NotYetOpen(_), ..) | (model::BuildError::Locked(_), ..) => (),
+        _ => return Err(refusal.into()),
+    }
+    if force {
+        say!(quiet, "Proceeding anyway (--force).");
     }
     Ok(())
 }
 
-fn print_items(temp_dir: &str) -> anyhow::Result<()> {
+fn print_items(temp_dir: &path::Path) -> anyhow::Result<()> {
     for entry in WalkDir::new(temp_dir)
         .min_depth(1)
         .max_depth(1)
@@ -177,7 +110,29 @@ fn print_items(temp_dir: &str) -> anyhow::Result<()> {
 static CONFIG_TOML_INIT: &str = r#"
 token = "<bearer token>"
 # Replace <bearer token> by an authorization token for Canvas 
+# Alternatively, keep the token out of this file by reading it from another file:
+# token_file = "~/.config/kerchief/token"
 domain = "example.instructure.com"
+# The domain may be left out if the environment variable KERCHIEF_DOMAIN is set.
+# The assignment to use when no key is given, e.g. `kerchief submit`. With a single
+# assignment in this file, that one is the default.
+# default_assignment = "1"
+# A submission larger than this many bytes in total is refused (the default is 100 MB).
+# max_upload_bytes = 104857600
+# The upload of a single file is given up after this many seconds (the default is 300).
+# upload_timeout_secs = 300
+# Courses and assignments are fetched from Canvas 100 at a time, the most Canvas allows.
+# per_page = 100
+# An optional cap on the number of courses or assignments fetched.
+# max_items = 500
+# Dates are shown in the local timezone of the system, unless another one is named.
+# timezone = "Europe/Stockholm"
+
+# Options added to every include, and patterns of files to leave out of every directory.
+# An include giving e.g. its own "compression=..." overrides the default one.
+# [defaults]
+# options = ["zip"]
+# exclude = ["target/", "*.o"]
 
 [course]
 name = "Canvas course name"
@@ -192,67 +147,1453 @@ name = "Canvas course name"
 #    to the named assignment.
 
 name = "Canvas assignment name"
+# Instead of by name, an assignment may be identified by the url of its page on Canvas:
+# url = "https://example.instructure.com/courses/1234/assignments/5678"
 include = [ "path/to/a/file.txt", "path/to/another/file.txt" ]
+# An include may also carry options, e.g. to zip a directory without compression:
+# include = [ { path = "path/to/a/dir", options = ["zip", "compression=stored"] } ]
+# With the option "respect_gitignore", files ignored by git are left out of a directory.
+# The option "content_type=<mime type>" overrides the content type of the uploaded file.
+# A path outside this directory, e.g. in your home directory, needs the option "outside_root".
+# To upload all of the includes as a single zip archive instead, name the archive. Each include
+# keeps its name inside it, and the options "zip" and "content_type" do not apply.
+# bundle = "submission.zip"
+# An optional reminder of what the assignment is, shown when submitting.
+# note = "Lab report and code for the first lab"
+# Optional shell commands run from this directory before staging and after submitting.
+# pre_submit = "make report"
+# post_submit = "echo submitted >> log.txt"
 "#;
 
-fn main() -> anyhow::Result<()> {
+/// Exit codes distinguishing the causes of a failure.
+const EXIT_FAILURE: i32 = 1;
+const EXIT_CONFIG: i32 = 2;
+const EXIT_CANVAS: i32 = 3;
+const EXIT_NO_ROOT: i32 = 4;
+
+fn exit_code(error: &anyhow::Error) -> i32 {
+    if let Some(e) = error.downcast_ref::<RootError>() {
+        match e {
+            RootError::NotFound => EXIT_NO_ROOT,
+            RootError::CurrentDir(_) => EXIT_FAILURE,
+        }
+    } else if let Some(e) = error.downcast_ref::<model::BuildError>() {
+        match e {
+            model::BuildError::Canvas(_) => EXIT_CANVAS,
+            model::BuildError::Stage(_) => EXIT_FAILURE,
+            _ => EXIT_CONFIG,
+        }
+    } else if error.downcast_ref::<model::ParseError>().is_some() {
+        EXIT_CONFIG
+    } else if error.downcast_ref::<canvas::Error>().is_some() {
+        EXIT_CANVAS
+    } else {
+        EXIT_FAILURE
+    }
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("error: {}", e);
+        process::exit(exit_code(&e));
+    }
+}
+
+/// Log to stderr at a level given by the number of `--verbose` flags: the Canvas requests at
+/// debug level, and at trace level the response bodies too. Only warnings are logged otherwise.
+fn init_logging(verbosity: u64) {
+    let level = match verbosity {
+        0 => log::LevelFilter::Warn,
+        1 => log::LevelFilter::Debug,
+        _ => log::LevelFilter::Trace,
+    };
+    env_logger::Builder::new()
+        .filter_level(log::LevelFilter::Warn)
+        .filter_module("canvas", level)
+        .filter_module("model", level)
+        .filter_module("kerchief", level)
+        .init();
+}
+
+fn run() -> anyhow::Result<()> {
     let matches = App::new("Kerchief")
         .version("0.1-alpha")
         .author("rosensymmetri <o.berndal@gmail.com>")
         .about("Upload assignments to canvas")
+        .arg(
+            Arg::with_name("course")
+                .long("course")
+                .value_name("NAME|ID")
+                .global(true)
+                .help(
+                    "use this course instead of the one in `kerchief.toml`; with `init`, the \
+                     course to write into it",
+                ),
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .short("v")
+                .long("verbose")
+                .multiple(true)
+                .global(true)
+                .help("log the requests made to Canvas; given twice, also log the responses"),
+        )
         .subcommand(
             SubCommand::with_name("init")
-                .about("initialize a `kerchief.toml` configuration file in current directory"),
+                .about("initialize a `kerchief.toml` configuration file in current directory")
+                .arg(
+                    Arg::with_name("yes")
+                        .short("y")
+                        .long("yes")
+                        .help(
+                            "overwrite an existing `kerchief.toml`, or a different token with \
+                             --no-secret, without asking",
+                        ),
+                )
+                .arg(
+                    Arg::with_name("domain")
+                        .long("domain")
+                        .value_name("DOMAIN")
+                        .help("the Canvas domain, e.g. example.instructure.com"),
+                )
+                .arg(
+                    Arg::with_name("token")
+                        .long("token")
+                        .value_name("TOKEN")
+                        .help("the authorization token for Canvas"),
+                )
+                .arg(
+                    Arg::with_name("no-secret")
+                        .long("no-secret")
+                        .requires("token")
+                        .help(
+                            "write the token to `~/.config/kerchief/token` and refer to that \
+                             file, instead of writing the token into `kerchief.toml`",
+                        ),
+                ),
         )
         .subcommand(
-            SubCommand::with_name("submit")
-                .about("submit the homework with the given KEY, as specified in `kerchief.toml`")
+            SubCommand::with_name("diff")
+                .about("compare the files that would be submitted to those of the last submission")
+                .arg(
+                    Arg::with_name("key").value_name("KEY").index(1).help(
+                        "the assignment key; if omitted, the default assignment or one picked \
+                         from a menu",
+                    ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("show")
+                .about(
+                    "show the details of the assignment with the given KEY and its latest \
+                     submission",
+                )
                 .arg(
-                    Arg::with_name("key")
-                        .value_name("KEY")
+                    Arg::with_name("key").value_name("KEY").index(1).help(
+                        "the assignment key; if omitted, the default assignment or one picked \
+                         from a menu",
+                    ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("history")
+                .about("list the earlier submissions to the assignment with the given KEY")
+                .arg(
+                    Arg::with_name("attempt")
+                        .long("attempt")
+                        .value_name("N")
+                        .validator(|n| match n.parse::<u64>() {
+                            Ok(n) if n > 0 => Ok(()),
+                            _ => Err(String::from("expected a positive number")),
+                        })
+                        .help("show the details of attempt N, counting from 1"),
+                )
+                .arg(
+                    Arg::with_name("key").value_name("KEY").index(1).help(
+                        "the assignment key; if omitted, the default assignment or one picked \
+                         from a menu",
+                    ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("stage")
+                .about(
+                    "stage the files of the assignment with the given KEY into a directory, \
+                     without contacting Canvas",
+                )
+                .arg(
+                    Arg::with_name("key").value_name("KEY").index(1).help(
+                        "the assignment key; if omitted, the default assignment",
+                    ),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .value_name("DIR")
                         .required(true)
-                        .index(1),
+                        .help("the directory to stage into, which must be empty or missing"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("status")
+                .about("show the due date and the latest submission of every assignment")
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .possible_values(&["list", "table"])
+                        .default_value("list")
+                        .help("print a line per assignment, or a table with aligned columns"),
+                )
+                .arg(
+                    Arg::with_name("group")
+                        .long("group")
+                        .help("list the assignments under their assignment groups on Canvas"),
+                )
+                .arg(
+                    Arg::with_name("since")
+                        .long("since")
+                        .value_name("DATE")
+                        .validator(|date| match parse_date(&date) {
+                            Some(_) => Ok(()),
+                            None => Err(String::from("expected a date such as 2021-02-15")),
+                        })
+                        .help(
+                            "only list the assignments due, or submitted to, on or after DATE, \
+                             given as YYYY-MM-DD",
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("submit")
+                .about(
+                    "submit the homework with the given KEY, as specified in `kerchief.toml`; a \
+                     glob such as 'lab*' submits every matching assignment",
+                )
+                .arg(
+                    Arg::with_name("key").value_name("KEY").index(1).help(
+                        "the assignment key; if omitted, the default assignment or one picked \
+                         from a menu",
+                    ),
+                )
+                .arg(
+                    Arg::with_name("include")
+                        .long("include")
+                        .value_name("PATH[:OPTIONS]")
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help(
+                            "also submit PATH, with comma separated options as in \
+                             `kerchief.toml`, e.g. `notes:zip,respect_gitignore`",
+                        ),
+                )
+                .arg(
+                    Arg::with_name("name")
+                        .long("name")
+                        .value_name("NEWNAME")
+                        .help("upload the single staged file under the name NEWNAME"),
+                )
+                .arg(
+                    Arg::with_name("force")
+                        .long("force")
+                        .help(
+                            "attempt the submission even if the assignment appears to be locked \
+                             or not yet open",
+                        ),
+                )
+                .arg(
+                    Arg::with_name("jobs")
+                        .short("j")
+                        .long("jobs")
+                        .value_name("N")
+                        .default_value("4")
+                        .validator(|n| match n.parse::<usize>() {
+                            Ok(n) if n > 0 => Ok(()),
+                            _ => Err(String::from("expected a positive number")),
+                        })
+                        .help("upload at most N files at a time"),
+                )
+                .arg(
+                    Arg::with_name("max-size")
+                        .long("max-size")
+                        .value_name("BYTES")
+                        .validator(|n| match n.parse::<u64>() {
+                            Ok(_) => Ok(()),
+                            Err(_) => Err(String::from("expected a number of bytes")),
+                        })
+                        .help("refuse to upload more than BYTES in total, overriding `max_upload_bytes`"),
+                )
+                .arg(
+                    Arg::with_name("keep-temp")
+                        .long("keep-temp")
+                        .help("keep the staged payload in `.kerchief` instead of removing it"),
+                )
+                .arg(
+                    Arg::with_name("yes")
+                        .short("y")
+                        .long("yes")
+                        .help("answer yes to all confirmations, including resubmitting"),
+                )
+                .arg(
+                    Arg::with_name("quiet")
+                        .short("q")
+                        .long("quiet")
+                        .help("print only the id of the submission, implies --yes"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("config")
+                .about("inspect the configuration in `kerchief.toml`")
+                .subcommand(
+                    SubCommand::with_name("show")
+                        .about("print the configuration as kerchief parsed it, token redacted")
+                        .arg(
+                            Arg::with_name("json")
+                                .long("json")
+                                .help("print JSON instead of TOML"),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("whoami")
+                .about("show the Canvas user that the token in `kerchief.toml` belongs to"),
+        )
+        .subcommand(
+            SubCommand::with_name("doctor")
+                .about("check the configuration, the connection to Canvas and the include paths"),
+        )
+        .subcommand(
+            SubCommand::with_name("clean")
+                .about(
+                    "remove the `.kerchief` working directory next to `kerchief.toml`, \
+                     including the cache of zipped directories",
                 ),
         )
         .get_matches();
-    if let ("init", _) = matches.subcommand() {
-        initialize();
+    init_logging(matches.occurrences_of("verbose"));
+    if let ("init", Some(sub_m)) = matches.subcommand() {
+        initialize(
+            sub_m.value_of("domain"),
+            sub_m.value_of("course"),
+            sub_m.value_of("token"),
+            sub_m.is_present("no-secret"),
+            sub_m.is_present("yes"),
+        )?;
+    } else if let ("clean", _) = matches.subcommand() {
+        clean()?;
+    } else if let ("whoami", _) = matches.subcommand() {
+        whoami()?;
+    } else if let ("doctor", _) = matches.subcommand() {
+        doctor()?;
+    } else if let ("config", Some(config_matches)) = matches.subcommand() {
+        match config_matches.subcommand() {
+            ("show", Some(show_matches)) => show_config(show_matches.is_present("json"))?,
+            _ => println!("{}", config_matches.usage()),
+        }
+    } else if let ("diff", Some(diff_matches)) = matches.subcommand() {
+        let mut store = model::Wall::try_from_path(find_config()?)?;
+        if let Some(course) = diff_matches.value_of("course") {
+            store.override_course(config::Identifier::from(course));
+        }
+        let key = key_or_pick(&store, diff_matches.value_of("key"))?;
+        diff(&store, &key)?;
+    } else if let ("show", Some(show_matches)) = matches.subcommand() {
+        let mut store = model::Wall::try_from_path(find_config()?)?;
+        if let Some(course) = show_matches.value_of("course") {
+            store.override_course(config::Identifier::from(course));
+        }
+        let key = key_or_pick(&store, show_matches.value_of("key"))?;
+        show(&store, &key)?;
+    } else if let ("history", Some(history_matches)) = matches.subcommand() {
+        let mut store = model::Wall::try_from_path(find_config()?)?;
+        if let Some(course) = history_matches.value_of("course") {
+            store.override_course(config::Identifier::from(course));
+        }
+        let key = key_or_pick(&store, history_matches.value_of("key"))?;
+        match history_matches.value_of("attempt") {
+            // validated by clap
+            Some(attempt) => show_attempt(&store, &key, attempt.parse().unwrap())?,
+            None => history(&store, &key)?,
+        }
+    } else if let ("stage", Some(stage_matches)) = matches.subcommand() {
+        let store = model::Wall::try_from_path(find_config()?)?;
+        // picking from a menu would fetch the assignment names
+        let key = match stage_matches.value_of("key") {
+            Some(key) => key,
+            None => store
+                .default_assignment_key()
+                .ok_or_else(|| anyhow::anyhow!("{}", missing_key_message(&store)))?,
+        };
+        // required by clap
+        let output = path::Path::new(stage_matches.value_of("output").unwrap());
+        stage(&store, key, output)?;
+    } else if let ("status", Some(status_matches)) = matches.subcommand() {
+        let mut store = model::Wall::try_from_path(find_config()?)?;
+        if let Some(course) = status_matches.value_of("course") {
+            store.override_course(config::Identifier::from(course));
+        }
+        status(
+            &store,
+            status_matches.value_of("format") == Some("table"),
+            status_matches.is_present("group"),
+            status_matches.value_of("since").and_then(parse_date),
+        )?;
     } else if let ("submit", Some(submit_matches)) = matches.subcommand() {
-        // key is mandatory argument -> we can unwrap
-        let key = submit_matches.value_of("key").unwrap();
-        let store = model::Wall::try_from_path("kerchief.toml")?;
-        println!("Submit to {}.", store.get_assignment_name(key)?);
+        let mut store = model::Wall::try_from_path(find_config()?)?;
+        if let Some(course) = submit_matches.value_of("course") {
+            store.override_course(config::Identifier::from(course));
+        }
+        // unlike the includes of `kerchief.toml`, those on the command line are relative to the
+        // working directory
+        let cwd = env::current_dir()?;
+        for include in submit_matches.values_of("include").into_iter().flatten() {
+            store.add_include(config::Path::from(include).relative_to(&cwd))?;
+        }
+        let key = match submit_matches.value_of("key") {
+            // a quiet submission is never interactive
+            None if submit_matches.is_present("quiet")
+                && store.default_assignment_key().is_none() =>
+            {
+                anyhow::bail!("{}", missing_key_message(&store))
+            }
+            key => key_or_pick(&store, key)?,
+        };
+        let key = key.as_str();
+        let flags = SubmitFlags {
+            force: submit_matches.is_present("force"),
+            name: submit_matches.value_of("name").map(str::to_owned),
+            // validated by clap
+            jobs: submit_matches.value_of("jobs").unwrap().parse().unwrap(),
+            max_size: submit_matches
+                .value_of("max-size")
+                .map(|n| n.parse().unwrap()),
+            keep_temp: submit_matches.is_present("keep-temp"),
+            yes: submit_matches.is_present("yes") || submit_matches.is_present("quiet"),
+            quiet: submit_matches.is_present("quiet"),
+        };
+        if is_key_pattern(key) {
+            submit_matching(&store, key, &flags)?;
+        } else {
+            submit(&store, key, &flags)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The flags of the `submit` subcommand.
+struct SubmitFlags {
+    force: bool,
+    name: Option<String>,
+    jobs: usize,
+    max_size: Option<u64>,
+    keep_temp: bool,
+    yes: bool,
+    quiet: bool,
+}
+
+/// The prompts of `submit`, which `--yes` answers in advance.
+struct CliPrompts<'a> {
+    store: &'a model::Wall,
+    flags: &'a SubmitFlags,
+}
+
+impl model::SubmitPrompts for CliPrompts<'_> {
+    type Error = anyhow::Error;
+
+    fn resubmit(&mut self, previous: &canvas::Submission) -> anyhow::Result<bool> {
+        say!(
+            self.flags.quiet,
+            "You already submitted at {}.",
+            submission_time(self.store, previous)
+        );
+        Ok(self.flags.yes || confirm("Resubmit?")?)
+    }
+
+    fn pre_submit(&mut self, hook: &str, root: &path::Path) -> anyhow::Result<()> {
+        run_hook(hook, root, self.flags.quiet)
+    }
 
-        let upload_dir = stage_includes(&store, key)?;
+    fn upload(&mut self, payload: &model::Payload) -> anyhow::Result<bool> {
+        let quiet = self.flags.quiet;
+        if self.flags.keep_temp {
+            say!(
+                quiet,
+                "Keeping the staged payload in {}.",
+                payload.dir.display()
+            );
+        }
+        if !quiet {
+            println!(
+                "Preparing to upload the following items (located in {}).",
+                payload.dir.display()
+            );
+            print_items(&payload.dir)?;
+        }
+        Ok(self.flags.yes || confirm("Proceed?")?)
+    }
+
+    fn resume(&mut self, resumable: usize) -> anyhow::Result<bool> {
+        let quiet = self.flags.quiet;
+        say!(
+            quiet,
+            "An interrupted submission already uploaded {} of these files.",
+            resumable
+        );
+        let resume = self.flags.yes || confirm("Reuse them instead of uploading them again?")?;
+        if resume {
+            say!(quiet, "Resuming the upload.");
+        }
+        Ok(resume)
+    }
+}
+
+/// Submit the includes of the assignment `key`, after showing what is about to be submitted and
+/// asking for confirmation. With `quiet`, only the id of the submission is printed. Returns the
+/// id of the submission, or `None` if the user cancelled.
+fn submit(store: &model::Wall, key: &str, flags: &SubmitFlags) -> anyhow::Result<Option<u64>> {
+    let quiet = flags.quiet;
+    let name = with_spinner("Fetching the assignment", || store.get_assignment_name(key))?;
+    say!(quiet, "Submit to {}.", name);
+    if let Some(note) = store.get_assignment_note(key)? {
+        say!(quiet, "    {}", note);
+    }
+
+    let assignment = store.get_assignment(key)?;
+    match assignment.due_at() {
+        Some(due_at) => say!(
+            quiet,
+            "Due at {}.",
+            store.display_time(due_at).format(DATE_FORMAT)
+        ),
+        None => say!(quiet, "No due date."),
+    }
+    if let Some(points) = assignment.points_possible() {
+        say!(quiet, "Out of {} points.", points);
+    }
+    if let Some(html_url) = assignment.html_url() {
+        say!(quiet, "On Canvas: {}", html_url);
+    }
+    if assignment.is_group_assignment() {
+        say!(
+            quiet,
+            "This is a group assignment: the submission is made on behalf of your group."
+        );
+    }
+    // a text entry assignment takes its text from stdin, when it is piped
+    let text_entry = !assignment.accepts(canvas::ONLINE_UPLOAD)
+        && assignment.accepts(canvas::ONLINE_TEXT_ENTRY)
+        && !io::stdin().is_terminal();
+    if text_entry && !flags.yes {
+        anyhow::bail!(
+            "the text entry is read from stdin, which leaves no way to confirm; pass --yes to submit"
+        );
+    }
+    warn_unavailable(store, key, flags.force, quiet)?;
+
+    // the text is read before the hook runs, so that the hook cannot take any of it
+    let text = if text_entry {
+        let mut body = String::new();
+        io::stdin().read_to_string(&mut body)?;
+        if body.trim().is_empty() {
+            anyhow::bail!("no text to submit was given on stdin");
+        }
+        Some(body)
+    } else {
+        None
+    };
+
+    let opts = model::SubmitOptions {
+        jobs: flags.jobs,
+        max_size: flags.max_size,
+        keep_temp: flags.keep_temp,
+        force: flags.force,
+        name: flags.name.clone(),
+        text,
+    };
+    let submitted = store.submit(key, opts, &mut CliPrompts { store, flags });
+    let receipt = match submitted {
+        Ok(Some(receipt)) => receipt,
+        Ok(None) => {
+            println!("Submission cancelled.");
+            return Ok(None);
+        }
+        Err(e) => {
+            return Err(match e.downcast_ref::<model::BuildError>() {
+                Some(model::BuildError::NotYetOpen(_) | model::BuildError::Locked(_)) => {
+                    anyhow::anyhow!("{}, use --force to attempt the submission anyway", e)
+                }
+                _ => e,
+            })
+        }
+    };
+    let submission = &receipt.submission;
+
+    if quiet {
+        println!("{}", submission.id());
+    } else {
         println!(
-            "Preparing to upload the following items (located in {}).",
-            &upload_dir
+            "Successful submission (id {}), recorded by Canvas at {}.",
+            submission.id(),
+            submission_time(store, submission)
         );
-        print_items(&upload_dir)?;
-        loop {
-            println!("Proceed? (y/n) ");
-            let mut line = String::new();
-            let stdin = io::stdin();
-            stdin.lock().read_line(&mut line)?;
-            if line.starts_with(&['y', 'Y'][..]) {
-                upload_and_submit(store, key, &upload_dir)?;
-                println!("Successful submission.");
-                break;
-            } else if line.starts_with(&['n', 'N'][..]) {
-                println!("Submission cancelled.");
-                break;
+        if let Some(late) = lateness(submission) {
+            println!("Canvas marks the submission {}.", late);
+        }
+        for (name, file_id) in &receipt.files {
+            println!("  {} (file id {})", name, file_id);
+        }
+    }
+    if let Some(hook) = store.get_post_submit_hook(key)? {
+        // the submission is already made, so a failing hook is only reported
+        if let Err(e) = run_hook(hook, store.root(), quiet) {
+            eprintln!("{}", e);
+        }
+    }
+    Ok(Some(submission.id()))
+}
+
+/// Whether `key` is a glob pattern rather than a plain assignment key.
+fn is_key_pattern(key: &str) -> bool {
+    key.contains(&['*', '?', '['][..])
+}
+
+/// Submit every assignment whose key matches the glob `pattern`, one after the other, and
+/// summarize the outcome per key. Fails if any of the submissions failed.
+fn submit_matching(store: &model::Wall, pattern: &str, flags: &SubmitFlags) -> anyhow::Result<()> {
+    let keys = store.get_matching_keys(pattern)?;
+    say!(flags.quiet, "Matched the assignments: {}.", keys.join(", "));
+    if !flags.yes && !confirm("Submit to all of them?")? {
+        println!("Submission cancelled.");
+        return Ok(());
+    }
+
+    let mut outcomes = Vec::new();
+    for key in &keys {
+        say!(flags.quiet, "\n[{}]", key);
+        let outcome = submit(store, key, flags);
+        if let Err(e) = &outcome {
+            eprintln!("error: {}", e);
+        }
+        outcomes.push((key, outcome));
+    }
+
+    say!(flags.quiet, "\nSummary:");
+    let mut failures = 0;
+    for (key, outcome) in outcomes {
+        match outcome {
+            Ok(Some(id)) => say!(flags.quiet, "    {}: submitted (id {})", key, id),
+            Ok(None) => say!(flags.quiet, "    {}: not submitted", key),
+            Err(e) => {
+                failures += 1;
+                say!(flags.quiet, "    {}: failed: {}", key, e);
             }
         }
     }
+    if failures > 0 {
+        anyhow::bail!("{} of {} submissions failed", failures, keys.len());
+    }
+    Ok(())
+}
+
+/// Run the shell command `hook` from the kerchief root, forwarding its output (to stderr if
+/// `quiet`).
+fn run_hook(hook: &str, root: &path::Path, quiet: bool) -> anyhow::Result<()> {
+    say!(quiet, "Running `{}`.", hook);
+    let stdout = if quiet {
+        process::Stdio::from(io::stderr())
+    } else {
+        process::Stdio::inherit()
+    };
+    Ok(model::run_hook(hook, root, stdout)?)
+}
 
+/// Show a spinner with `message` while `f` runs, e.g. while waiting on Canvas. The spinner is
+/// hidden if stdout is not a terminal.
+fn with_spinner<T>(message: &'static str, f: impl FnOnce() -> T) -> T {
+    let spinner = ProgressBar::new_spinner();
+    spinner.set_draw_target(ProgressDrawTarget::stdout());
+    spinner.set_message(message);
+    spinner.enable_steady_tick(time::Duration::from_millis(100));
+    let result = f();
+    spinner.finish_and_clear();
+    result
+}
+
+/// Ask the user a yes/no `question` until they answer either. Fails if stdin ends first, e.g.
+/// when it is not a terminal, rather than asking again and again.
+fn confirm(question: &str) -> anyhow::Result<bool> {
+    loop {
+        println!("{} (y/n) ", question);
+        let mut line = String::new();
+        let stdin = io::stdin();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            anyhow::bail!("no confirmation provided (use --yes)");
+        }
+        if line.starts_with(&['y', 'Y'][..]) {
+            return Ok(true);
+        } else if line.starts_with(&['n', 'N'][..]) {
+            return Ok(false);
+        }
+    }
+}
+
+/// Ask the user to pick one of `items` by its number, until they answer with a valid one.
+/// Returns the index of the picked item.
+fn pick(question: &str, items: &[String]) -> anyhow::Result<usize> {
+    for (i, item) in items.iter().enumerate() {
+        println!("  {}) {}", i + 1, item);
+    }
+    loop {
+        println!("{} (1-{}) ", question, items.len());
+        let mut line = String::new();
+        if io::stdin().lock().read_line(&mut line)? == 0 {
+            anyhow::bail!("no choice was made (give the assignment key)");
+        }
+        match line.trim().parse::<usize>() {
+            Ok(n) if (1..=items.len()).contains(&n) => return Ok(n - 1),
+            _ => {}
+        }
+    }
+}
+
+fn missing_key_message(store: &model::Wall) -> String {
+    let keys: Vec<_> = store.assignment_keys().collect();
+    format!(
+        "no assignment key given; the available keys are: {}",
+        keys.join(", ")
+    )
+}
+
+/// The assignment key `key` or, if it is omitted, the default assignment. Without a default and
+/// with stdin a terminal, the user picks an assignment from a menu of those in the config.
+fn key_or_pick(store: &model::Wall, key: Option<&str>) -> anyhow::Result<String> {
+    if let Some(key) = key.or_else(|| store.default_assignment_key()) {
+        return Ok(key.to_owned());
+    }
+    let keys: Vec<_> = store.assignment_keys().collect();
+    if !io::stdin().is_terminal() || keys.is_empty() {
+        anyhow::bail!("{}", missing_key_message(store));
+    }
+    let items = with_spinner("Fetching the assignments", || {
+        keys.iter()
+            .map(|key| {
+                let name = store.get_assignment_name(key)?;
+                Ok(format!("{} ({})", key, name))
+            })
+            .collect::<Result<Vec<_>, model::BuildError>>()
+    })?;
+    let i = pick("Which assignment?", &items)?;
+    Ok(keys[i].to_owned())
+}
+
+/// Stage the includes of the assignment `key` into the directory `output` and list them. Only the
+/// config is read, so this works without a connection to Canvas.
+fn stage(store: &model::Wall, key: &str, output: &path::Path) -> anyhow::Result<()> {
+    let staging = model::StagingDir::at(output)?;
+    let payload = store.stage_includes(key, &staging, Some(store.get_max_upload_bytes()))?;
+    println!("Staged the following items in {}.", payload.dir.display());
+    print_items(&payload.dir)?;
+    Ok(())
+}
+
+/// Compare the names of the files which would be staged for `key` to the names of the files in
+/// the latest submission, marking local additions with `+`, files only in the submission with
+/// `-` and files in both with `=`.
+fn diff(store: &model::Wall, key: &str) -> anyhow::Result<()> {
+    let staging = model::StagingDir::create(store.root(), false)?;
+    let payload = store.stage_includes(key, &staging, None)?;
+    let mut local = BTreeSet::new();
+    for entry in fs::read_dir(&payload.dir)? {
+        local.insert(entry?.file_name().to_string_lossy().into_owned());
+    }
+
+    let latest_submission = with_spinner("Fetching the latest submission", || {
+        store.get_latest_submission(key)
+    })?;
+    let remote: BTreeSet<_> = match &latest_submission {
+        Some(submission) => submission
+            .attachments()
+            .iter()
+            .map(|a| a.display_name().to_owned())
+            .collect(),
+        None => {
+            println!("Nothing has been submitted yet.");
+            BTreeSet::new()
+        }
+    };
+
+    for name in local.union(&remote) {
+        let mark = match (local.contains(name), remote.contains(name)) {
+            (true, true) => '=',
+            (true, false) => '+',
+            _ => '-',
+        };
+        println!("  {} {}", mark, name);
+    }
+    Ok(())
+}
+
+/// Print the details of the assignment `key`: its note, due date, points, page on Canvas and
+/// description, and whether and when it was last submitted.
+fn show(store: &model::Wall, key: &str) -> anyhow::Result<()> {
+    let status = with_spinner("Fetching the latest submission", || {
+        store.get_assignment_status(key)
+    })?;
+    let assignment = status.assignment();
+    let now = Local::now();
+    println!("{}", assignment.name());
+    if let Some(note) = store.get_assignment_note(key)? {
+        println!("    {}", note);
+    }
+    match assignment.due_at() {
+        Some(due_at) if status.is_overdue(&now) => println!(
+            "Due at {}, OVERDUE.",
+            store.display_time(due_at).format(DATE_FORMAT)
+        ),
+        Some(due_at) => println!("Due at {}.", store.display_time(due_at).format(DATE_FORMAT)),
+        None => println!("No due date."),
+    }
+    if !status.is_open(&now) {
+        if let Some(unlock_at) = assignment.unlock_at() {
+            println!(
+                "Opens at {}.",
+                store.display_time(unlock_at).format(DATE_FORMAT)
+            );
+        }
+    } else if let Some(lock_at) = assignment.lock_at() {
+        let verb = if status.is_locked(&now) {
+            "Locked since"
+        } else {
+            "Locks at"
+        };
+        println!(
+            "{} {}.",
+            verb,
+            store.display_time(lock_at).format(DATE_FORMAT)
+        );
+    }
+    if let Some(points) = assignment.points_possible() {
+        println!("Out of {} points.", points);
+    }
+    if let Some(html_url) = assignment.html_url() {
+        println!("On Canvas: {}", html_url);
+    }
+    if let Some(description) = assignment.description().map(html_to_text) {
+        if !description.is_empty() {
+            println!();
+            for line in description.lines() {
+                println!("    {}", line);
+            }
+            println!();
+        }
+    }
+
+    match status.submission() {
+        Some(submission) => {
+            let submitted_at = submission_time(store, submission);
+            match lateness(submission) {
+                Some(late) => println!("Last submitted at {}, {}.", submitted_at, late),
+                None => println!("Last submitted at {}.", submitted_at),
+            }
+            match (submission.grade(), submission.score()) {
+                (Some(grade), Some(score)) => println!("Graded {} ({} points).", grade, score),
+                (Some(grade), None) => println!("Graded {}.", grade),
+                _ => (),
+            }
+        }
+        None => println!("Nothing has been submitted yet."),
+    }
     Ok(())
 }
 
-fn initialize() {
-    let response = fs::write("kerchief.toml", CONFIG_TOML_INIT.as_bytes());
-    // let response = cfg_file.write();
-    match response {
-        Ok(_) => println!("Successfully wrote a template configuration to `kerchief.toml`."),
-        Err(e) => eprintln!("Failed to write to `kerchief.toml`: {}", e),
+/// The text of the HTML `html`, without its tags, with a line break after each paragraph and the
+/// common character entities decoded.
+fn html_to_text(html: &str) -> String {
+    let mut text = String::new();
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('>')
+            .map_or(rest.len(), |end| start + end + 1);
+        let tag = rest[start..end].to_ascii_lowercase();
+        if tag.starts_with("<br") || tag.starts_with("</p") || tag.starts_with("</li") {
+            text.push('\n');
+        }
+        rest = &rest[end..];
+    }
+    text.push_str(rest);
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    let lines: Vec<_> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+    lines.join("\n")
+}
+
+/// List the submitted attempts at the assignment `key`, each followed by the comments on it, or,
+/// if nothing has been submitted, say whether the assignment is overdue or locked.
+fn history(store: &model::Wall, key: &str) -> anyhow::Result<()> {
+    let status = with_spinner("Fetching the latest submission", || {
+        store.get_assignment_status(key)
+    })?;
+    if !status.is_submitted() {
+        println!("Nothing has been submitted yet.");
+        let now = Local::now();
+        if status.is_locked(&now) {
+            println!("The assignment is locked.");
+        } else if status.is_overdue(&now) {
+            println!("The assignment is overdue.");
+        }
+        return Ok(());
+    }
+    let (history, comments) = with_spinner("Fetching the submission history", || {
+        Ok::<_, model::BuildError>((
+            store.get_submission_history(key)?,
+            store.get_submission_comments(key)?,
+        ))
+    })?;
+    let mut attempts = BTreeSet::new();
+    for (i, submission) in history.iter().enumerate() {
+        let attempt = submission.attempt().unwrap_or(i as u64 + 1);
+        attempts.insert(attempt);
+        let submitted_at = submission_time(store, submission);
+        match lateness(submission) {
+            Some(late) => println!("Attempt {}, {}, {}", attempt, submitted_at, late),
+            None => println!("Attempt {}, {}", attempt, submitted_at),
+        }
+        if let Some(content) = submission.content() {
+            println!("  {}", content);
+        }
+        for comment in comments.iter().filter(|c| c.attempt() == Some(attempt)) {
+            print_comment(store, comment);
+        }
+    }
+    // comments which Canvas does not tie to one of the attempts
+    let other: Vec<_> = comments
+        .iter()
+        .filter(|c| {
+            !c.attempt()
+                .is_some_and(|attempt| attempts.contains(&attempt))
+        })
+        .collect();
+    if !other.is_empty() {
+        println!("Comments");
+        for comment in other {
+            print_comment(store, comment);
+        }
+    }
+    Ok(())
+}
+
+/// Print `comment` under an attempt of `history`, indented.
+fn print_comment(store: &model::Wall, comment: &canvas::SubmissionComment) {
+    println!(
+        "  {} commented at {}:",
+        comment.author_name(),
+        store.display_time(comment.created_at()).format(DATE_FORMAT)
+    );
+    for line in comment.comment().lines() {
+        println!("    {}", line);
+    }
+}
+
+/// Print the status of every assignment, by due date, either as a line per assignment or as a
+/// table. With `by_group`, the assignments are listed under the heading of their assignment
+/// group, in the order of the groups on Canvas, and those in no group come last. With `since`,
+/// only the assignments due or submitted to since then are listed.
+fn status(
+    store: &model::Wall,
+    as_table: bool,
+    by_group: bool,
+    since: Option<DateTime<Local>>,
+) -> anyhow::Result<()> {
+    let sections = with_spinner("Fetching the latest submissions", || {
+        let keys = store.get_keys_by_due_date()?;
+        let mut sections = Vec::new();
+        if by_group {
+            let groups = store.get_assignment_groups()?;
+            let grouped: BTreeSet<_> = groups.iter().flat_map(|(_, keys)| keys).collect();
+            let rest: Vec<_> = keys
+                .iter()
+                .filter(|key| !grouped.contains(key))
+                .copied()
+                .collect();
+            for (group, keys) in &groups {
+                sections.push((Some(group.name().to_owned()), keys.clone()));
+            }
+            if !rest.is_empty() {
+                sections.push((Some(String::from("Other")), rest));
+            }
+        } else {
+            sections.push((None, keys));
+        }
+
+        let mut statuses = Vec::new();
+        for (heading, keys) in sections {
+            let mut section = Vec::new();
+            for key in keys {
+                let status = store.get_assignment_status(key)?;
+                if since.is_none_or(|since| status.is_since(&since)) {
+                    section.push((key, status));
+                }
+            }
+            if !section.is_empty() {
+                statuses.push((heading, section));
+            }
+        }
+        Ok::<_, model::BuildError>(statuses)
+    })?;
+    let now = Local::now();
+    for (i, (heading, statuses)) in sections.into_iter().enumerate() {
+        if let Some(heading) = heading {
+            if i > 0 {
+                println!();
+            }
+            println!("{}", heading);
+        }
+        let mut table =
+            table::Table::new(&["key", "name", "due", "overdue", "locked", "submitted"]);
+        for (key, status) in statuses {
+            let assignment = status.assignment();
+            let due = match assignment.due_at() {
+                Some(due_at) => store.display_time(due_at).format(DATE_FORMAT).to_string(),
+                None => String::from("no due date"),
+            };
+            let yes_no = |b| if b { "yes" } else { "no" };
+            let overdue = yes_no(status.is_overdue(&now));
+            let locked = yes_no(status.is_locked(&now));
+            let submitted = match status.submission() {
+                Some(submission) => {
+                    let submitted_at = submission_time(store, submission);
+                    match lateness(submission) {
+                        Some(late) => format!("{}, {}", submitted_at, late),
+                        None => submitted_at,
+                    }
+                }
+                None => String::from("no"),
+            };
+            if as_table {
+                table.push_row(vec![
+                    key.to_owned(),
+                    assignment.name().to_owned(),
+                    due,
+                    overdue.to_owned(),
+                    locked.to_owned(),
+                    submitted,
+                ]);
+            } else {
+                println!("{}: {}", key, assignment.name());
+                println!(
+                    "  due {}, overdue: {}, locked: {}, submitted: {}",
+                    due, overdue, locked, submitted
+                );
+            }
+        }
+        if as_table {
+            print!("{}", table);
+        }
+    }
+    Ok(())
+}
+
+/// The start of the local day `date`, given as YYYY-MM-DD.
+fn parse_date(date: &str) -> Option<DateTime<Local>> {
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+    Local
+        .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+        .earliest()
+}
+
+/// When `submission` was made, in the display time zone, or "unknown time" if Canvas leaves the
+/// time out.
+fn submission_time(store: &model::Wall, submission: &canvas::Submission) -> String {
+    match submission.submitted_at() {
+        Some(submitted_at) => store
+            .display_time(submitted_at)
+            .format(DATE_FORMAT)
+            .to_string(),
+        None => String::from("unknown time"),
+    }
+}
+
+/// "LATE by 2h 15m", if Canvas marks `submission` as late.
+fn lateness(submission: &canvas::Submission) -> Option<String> {
+    if !submission.is_late() {
+        return None;
+    }
+    Some(match submission.seconds_late() {
+        Some(seconds) => format!("LATE by {}", format_duration(seconds)),
+        None => String::from("LATE"),
+    })
+}
+
+/// A duration of `seconds` in days, hours and minutes, such as "1d 2h" or "2h 15m". Seconds are
+/// only shown for a duration shorter than a minute.
+fn format_duration(seconds: u64) -> String {
+    if seconds < 60 {
+        return format!("{}s", seconds);
+    }
+    let parts = [
+        (seconds / 86400, "d"),
+        (seconds % 86400 / 3600, "h"),
+        (seconds % 3600 / 60, "m"),
+    ];
+    let parts: Vec<_> = parts
+        .iter()
+        .filter(|(n, _)| *n > 0)
+        .map(|(n, unit)| format!("{}{}", n, unit))
+        .collect();
+    parts.join(" ")
+}
+
+/// Print the details of the submitted attempt number `attempt` at the assignment `key`.
+fn show_attempt(store: &model::Wall, key: &str, attempt: u64) -> anyhow::Result<()> {
+    let submission = with_spinner("Fetching the submission history", || {
+        store.get_submission_attempt(key, attempt)
+    })?;
+    println!(
+        "Attempt {}, {}",
+        attempt,
+        submission_time(store, &submission)
+    );
+    if let Some(late) = lateness(&submission) {
+        println!("Submitted {}.", late);
+    }
+    match (submission.score(), submission.grade()) {
+        (Some(score), Some(grade)) => println!("Graded: {} (score {}).", grade, score),
+        (Some(score), None) => println!("Graded: score {}.", score),
+        (None, Some(grade)) => println!("Graded: {}.", grade),
+        (None, None) => println!("Not graded."),
+    }
+    match submission.content() {
+        Some(canvas::SubmissionContent::Upload(attachments)) => {
+            println!("Uploaded files:");
+            for attachment in attachments {
+                println!(
+                    "  {} ({} bytes)",
+                    attachment.display_name(),
+                    attachment.size()
+                );
+            }
+        }
+        Some(content) => println!("Submitted as {}.", content),
+        None => (),
+    }
+    Ok(())
+}
+
+/// Print the config as parsed, as TOML or JSON, with the token redacted.
+fn show_config(json: bool) -> anyhow::Result<()> {
+    let store = model::Wall::try_from_path(find_config()?)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(store.config())?);
+    } else {
+        // via a `Value`, which places the plain values ahead of the tables as TOML requires
+        print!(
+            "{}",
+            toml::to_string(&toml::Value::try_from(store.config())?)?
+        );
+    }
+    Ok(())
+}
+
+fn whoami() -> anyhow::Result<()> {
+    let store = model::Wall::try_from_path(find_config()?)?;
+    let (token, domain) = (store.get_token()?, store.get_domain()?);
+    let user = with_spinner("Fetching the user", || canvas::get_self(token, domain))?;
+    match user.login_id() {
+        Some(login_id) => println!("{} ({}, id {})", user.name(), login_id, user.id()),
+        None => println!("{} (id {})", user.name(), user.id()),
+    }
+    Ok(())
+}
+
+/// The tally of the checks made by `doctor`, each printed as it is made.
+#[derive(Default)]
+struct Checklist {
+    failures: usize,
+}
+
+impl Checklist {
+    fn pass(&self, check: &str) {
+        println!("[ok]   {}", check);
+    }
+
+    fn fail(&mut self, check: &str, error: impl std::fmt::Display, hint: &str) {
+        println!("[fail] {}: {}", check, error);
+        println!("       {}", hint);
+        self.failures += 1;
+    }
+}
+
+/// Check the setup one step at a time, from finding `kerchief.toml` to the include paths of each
+/// assignment, and print a line per check with a hint for each failure. A failed check only
+/// skips the checks which depend on it.
+fn doctor() -> anyhow::Result<()> {
+    let mut checklist = Checklist::default();
+    let config = match find_config() {
+        Ok(config) => {
+            checklist.pass(&format!("found `{}`", config.display()));
+            config
+        }
+        Err(e) => {
+            checklist.fail(
+                "find a config file such as `kerchief.toml`",
+                e,
+                "run `kerchief init` to write a template configuration",
+            );
+            anyhow::bail!("1 check failed");
+        }
+    };
+    let store = match model::Wall::try_from_path(&config) {
+        Ok(store) => {
+            checklist.pass("parsed the config");
+            store
+        }
+        Err(e) => {
+            checklist.fail(
+                "parse the config",
+                e,
+                "compare it to the template which `kerchief init` writes",
+            );
+            anyhow::bail!("1 check failed");
+        }
+    };
+    let (token, domain) = match store
+        .get_token()
+        .and_then(|token| Ok((token, store.get_domain()?)))
+    {
+        Ok(credentials) => {
+            checklist.pass("found the token and the domain");
+            credentials
+        }
+        Err(e) => {
+            checklist.fail(
+                "find the token and the domain",
+                e,
+                "give the `token` or `token_file`, and the `domain`, in the config",
+            );
+            anyhow::bail!("1 check failed");
+        }
+    };
+
+    let connected = match with_spinner("Fetching the user", || canvas::get_self(token, domain)) {
+        Ok(user) => {
+            checklist.pass(&format!("reached {}", domain));
+            checklist.pass(&format!("the token belongs to {}", user.name()));
+            true
+        }
+        Err(canvas::Error::Reqwest(e)) if e.status().map(|s| s.as_u16()) == Some(401) => {
+            checklist.pass(&format!("reached {}", domain));
+            checklist.fail(
+                "authenticate with the token",
+                e,
+                "generate a new access token under Account > Settings on Canvas",
+            );
+            false
+        }
+        Err(e) => {
+            checklist.fail(
+                &format!("reach {}", domain),
+                e,
+                "check the `domain`, e.g. `example.instructure.com`, and your connection",
+            );
+            false
+        }
+    };
+
+    let course_resolved = connected
+        && match with_spinner("Fetching the courses", || store.get_course_id()) {
+            Ok(id) => {
+                checklist.pass(&format!("the course resolves (id {})", id));
+                true
+            }
+            Err(e) => {
+                checklist.fail(
+                    "resolve the course",
+                    e,
+                    "set `name` or `id` under [course] to one of the courses listed",
+                );
+                false
+            }
+        };
+    let keys: Vec<_> = store.assignment_keys().map(str::to_owned).collect();
+    for key in &keys {
+        if course_resolved {
+            match with_spinner("Fetching the assignments", || store.get_assignment_id(key)) {
+                Ok(id) => checklist.pass(&format!("assignment '{}' resolves (id {})", key, id)),
+                Err(e) => checklist.fail(
+                    &format!("resolve assignment '{}'", key),
+                    e,
+                    "set `name`, `id` or `url` of the assignment to one of those listed",
+                ),
+            }
+        }
+        match store.get_staged_includes(key) {
+            Ok(includes) => checklist.pass(&format!(
+                "the {} include(s) of '{}' exist",
+                includes.len(),
+                key
+            )),
+            Err(e) => checklist.fail(
+                &format!("find the includes of '{}'", key),
+                e,
+                "include paths are relative to the directory of `kerchief.toml`",
+            ),
+        }
+    }
+
+    match checklist.failures {
+        0 => {
+            println!("All checks passed.");
+            Ok(())
+        }
+        1 => anyhow::bail!("1 check failed"),
+        n => anyhow::bail!("{} checks failed", n),
+    }
+}
+
+/// Remove the `.kerchief` directory at the kerchief root, with the cache of zipped directories
+/// in it, reporting the space freed.
+fn clean() -> anyhow::Result<()> {
+    let work_dir = find_root()?.join(".kerchief");
+    if !work_dir.is_dir() {
+        println!("Nothing to clean.");
+        return Ok(());
+    }
+
+    let mut freed = 0;
+    for entry in WalkDir::new(&work_dir).into_iter().flatten() {
+        if entry.file_type().is_file() {
+            freed += entry.metadata()?.len();
+        }
+    }
+    fs::remove_dir_all(&work_dir)?;
+    println!("Removed {} ({} bytes freed).", work_dir.display(), freed);
+
+    Ok(())
+}
+
+fn initialize(
+    domain: Option<&str>,
+    course: Option<&str>,
+    token: Option<&str>,
+    no_secret: bool,
+    yes: bool,
+) -> anyhow::Result<()> {
+    if path::Path::new("kerchief.toml").exists()
+        && !yes
+        && !confirm("`kerchief.toml` already exists. Overwrite it?")?
+    {
+        println!("Nothing was written.");
+        return Ok(());
+    }
+    let token = match token {
+        Some(token) if no_secret => {
+            let path = config::expand_home(INIT_TOKEN_FILE)?;
+            // the token file may be shared with the configurations of other courses
+            let differs = match fs::read_to_string(&path) {
+                Ok(existing) => existing.trim() != token,
+                Err(_) => false,
+            };
+            if differs
+                && !yes
+                && !confirm(&format!(
+                    "`{}` already holds a different token. Overwrite it?",
+                    path.display()
+                ))?
+            {
+                println!("Nothing was written.");
+                return Ok(());
+            }
+            write_secret(&path, token).map_err(|e| {
+                anyhow::anyhow!("failed to write the token to `{}`: {}", path.display(), e)
+            })?;
+            println!("Wrote the token to `{}`.", path.display());
+            Some(InitToken::File(INIT_TOKEN_FILE))
+        }
+        Some(token) => Some(InitToken::Inline(token)),
+        None => None,
+    };
+    fs::write("kerchief.toml", init_template(domain, course, token))
+        .map_err(|e| anyhow::anyhow!("failed to write to `kerchief.toml`: {}", e))?;
+    println!("Successfully wrote a template configuration to `kerchief.toml`.");
+    Ok(())
+}
+
+/// Write `token` to the file at `path`, creating its directory. On unix, only the owner may
+/// read or write the file.
+fn write_secret(path: &path::Path, token: &str) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let file = options.open(path)?;
+    // the mode only applies to a new file, so an existing one is restricted here
+    #[cfg(unix)]
+    file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+    writeln!(&file, "{}", token)
+}
+
+/// Where `init --no-secret` keeps the token.
+static INIT_TOKEN_FILE: &str = "~/.config/kerchief/token";
+
+enum InitToken<'a> {
+    Inline(&'a str),
+    File(&'a str),
+}
+
+/// The `CONFIG_TOML_INIT` template with the placeholders of the given values filled in. A
+/// numeric course is taken as the id of the course.
+fn init_template(domain: Option<&str>, course: Option<&str>, token: Option<InitToken>) -> String {
+    let quote = |value: &str| toml::Value::from(value).to_string();
+    let mut template = String::from(CONFIG_TOML_INIT);
+    // The token placeholder and its comments are the lines before the domain.
+    let token_lines = match template.find("token = ").zip(template.find("domain = ")) {
+        Some((start, end)) => start..end,
+        None => 0..0,
+    };
+    match token {
+        Some(InitToken::Inline(token)) => {
+            template.replace_range(token_lines, &format!("token = {}\n", quote(token)));
+        }
+        Some(InitToken::File(path)) => {
+            template.replace_range(
+                token_lines,
+                &format!(
+                    "# The authorization token for Canvas is read from this file:\ntoken_file = {}\n",
+                    quote(path)
+                ),
+            );
+        }
+        None => {}
+    }
+    if let Some(domain) = domain {
+        template = template.replacen(
+            "domain = \"example.instructure.com\"",
+            &format!("domain = {}", quote(domain)),
+            1,
+        );
+    }
+    if let Some(course) = course {
+        let identifier = match course.parse::<u64>() {
+            Ok(id) => format!("id = {}", id),
+            Err(_) => format!("name = {}", quote(course)),
+        };
+        template = template.replacen("name = \"Canvas course name\"", &identifier, 1);
     }
+    template
 }
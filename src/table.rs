@@ -0,0 +1,82 @@
+//! A plain text table whose columns are as wide as their widest cell, as it appears in a terminal.
+
+use std::fmt;
+use unicode_width::UnicodeWidthStr;
+
+pub struct Table {
+    header: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(header: &[&str]) -> Self {
+        Self {
+            header: header.iter().map(|s| s.to_string()).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// Add a row. Cells beyond the number of columns of the header are dropped, missing ones
+    /// are left empty.
+    pub fn push_row(&mut self, mut row: Vec<String>) {
+        row.resize(self.header.len(), String::new());
+        self.rows.push(row);
+    }
+
+    fn widths(&self) -> Vec<usize> {
+        let mut widths: Vec<_> = self.header.iter().map(|cell| cell.width()).collect();
+        for row in &self.rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.width());
+            }
+        }
+        widths
+    }
+}
+
+fn write_row(f: &mut fmt::Formatter<'_>, row: &[String], widths: &[usize]) -> fmt::Result {
+    let mut line = String::new();
+    for (i, (cell, width)) in row.iter().zip(widths).enumerate() {
+        if i > 0 {
+            line.push_str(" | ");
+        }
+        line.push_str(cell);
+        // the padding goes by the displayed width, which a `{:width$}` format does not
+        line.push_str(&" ".repeat(width - cell.width()));
+    }
+    writeln!(f, "{}", line.trim_end())
+}
+
+impl fmt::Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let widths = self.widths();
+        write_row(f, &self.header, &widths)?;
+        let rule: Vec<_> = widths.iter().map(|width| "-".repeat(*width)).collect();
+        writeln!(f, "{}", rule.join("-+-"))?;
+        for row in &self.rows {
+            write_row(f, row, &widths)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn columns_align_by_displayed_width() {
+        let mut table = Table::new(&["name", "due"]);
+        table.push_row(vec![String::from("Räkneövning"), String::from("today")]);
+        table.push_row(vec![String::from("課題"), String::from("-")]);
+        table.push_row(vec![String::from("Lab 1")]);
+        assert_eq!(
+            table.to_string(),
+            "name        | due\n\
+             ------------+------\n\
+             Räkneövning | today\n\
+             課題        | -\n\
+             Lab 1       |\n"
+        );
+    }
+}